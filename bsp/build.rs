@@ -1,10 +1,19 @@
 #[allow(unused)]
 use std::io::Write;
 #[cfg(feature = "reg-config")]
-use st_mems_reg_config_conv::parser;
+use iis2dulpx_rs::asynchronous::reg_config::export;
 #[cfg(feature = "reg-config")]
 use std::path::Path;
 
+/// Converts a `reg_config` JSON file into a Rust constant, as `iis2dulpx-config export rust`.
+#[cfg(feature = "reg-config")]
+fn generate_rs_from_json(input_file: &Path, output_file: &Path, name: &str) {
+    let json = std::fs::read(input_file).unwrap();
+    let config = export::read_program(&json).unwrap();
+    let source = export::rust_source(name, "crate::iis2dulpx::reg_config", &config);
+    std::fs::write(output_file, source).unwrap();
+}
+
 fn main() {
     #[cfg(feature = "generate-memory-x")]
     {
@@ -52,7 +61,7 @@ fn main() {
     {
         let input_file = Path::new("src/config/iis2dulpx_fourd_orientation_detection.json");
         let output_file = Path::new("src/config/fsm_config.rs");
-        parser::generate_rs_from_json(&input_file, &output_file, "FOURD", "IIS2DULPX", false);
+        generate_rs_from_json(input_file, output_file, "FOURD");
 
         println!("cargo:rerun-if-changed=src/config/iis2dulpx_fourd_orientation_detection.json");
         println!("cargo:rerun-if-changed=src/config/fsm_config.rs");
@@ -62,7 +71,7 @@ fn main() {
     {
         let input_file = Path::new("src/config/iis2dulpx_vibration_monitoring.json");
         let output_file = Path::new("src/config/mlc_config.rs");
-        parser::generate_rs_from_json(&input_file, &output_file, "VIBRATION", "IIS2DULPX", false);
+        generate_rs_from_json(input_file, output_file, "VIBRATION");
 
         println!("cargo:rerun-if-changed=src/config/iis2dulpx_vibration_monitoring.json");
         println!("cargo:rerun-if-changed=src/config/mlc_config.rs");
@@ -14,6 +14,7 @@ where
 {
     use iis2dulpx::*;
     use iis2dulpx::prelude::*;
+    use iis2dulpx::timestamp::SensorTime;
 
     info!("Configuring the sensor");
     let mut sensor = Iis2dulpx::from_bus(bus, delay);
@@ -78,60 +79,55 @@ where
 
         // Read number of samples in FIFO
         let mut num = sensor.fifo_data_level_get().await.unwrap() as u16;
-        let mut ts: u32;
+        let mut ts: u64;
         writeln!(tx, "-- {} in FIFO", num).unwrap();
         while num > 0 {
             let curr_entry = (NUM_FIFO_ENTRY as u16) - num;
             num -= 1;
             let fdata = sensor.fifo_data_get(&md, &fifo_mode).await.unwrap();
-            match FifoSensorTag::try_from(fdata.tag).unwrap_or_default() {
-                FifoSensorTag::XlOnly2xTag => {
+            match fdata {
+                FifoSample::AccelPair(xl) => {
                     writeln!(
                         tx,
                         "{:2}_0: Acceleration [0][mg]:\t{:4.2}\t{:4.2}\t{:4.2}",
-                        curr_entry, fdata.xl[0].mg[0], fdata.xl[0].mg[1], fdata.xl[0].mg[2]
+                        curr_entry, xl[0].mg[0], xl[0].mg[1], xl[0].mg[2]
                     )
                     .unwrap();
                     writeln!(
                         tx,
                         "{:2}_1: Acceleration [1][mg]:\t{:4.2}\t{:4.2}\t{:4.2}",
-                        curr_entry, fdata.xl[1].mg[0], fdata.xl[1].mg[1], fdata.xl[1].mg[2]
+                        curr_entry, xl[1].mg[0], xl[1].mg[1], xl[1].mg[2]
                     )
                     .unwrap();
                 }
-                FifoSensorTag::XlTempTag => {
-                    if fifo_mode.xl_only == 0 {
-                        writeln!(
-                            tx,
-                            "{:2}: Acceleration [0][mg]:{:4.2}\t{:4.2}\t{:4.2}\tTemp[degC]:{:3.2}",
-                            curr_entry,
-                            fdata.xl[0].mg[0],
-                            fdata.xl[0].mg[1],
-                            fdata.xl[0].mg[2],
-                            fdata.heat.deg_c
-                        )
-                        .unwrap();
-                    } else {
-                        writeln!(
-                            tx,
-                            "{:2}: Acceleration [0][mg]:{:4.2}\t{:4.2}\t{:4.2}",
-                            curr_entry, fdata.xl[0].mg[0], fdata.xl[0].mg[1], fdata.xl[0].mg[2]
-                        )
-                        .unwrap();
-                    }
-                }
-                FifoSensorTag::TimestampTag => {
-                    ts = fdata.cfg_chg.timestamp / 100;
-                    writeln!(tx, "Timestamp:\t{} ms", ts).unwrap();
+                FifoSample::AccelTemp { xl, heat } => {
+                    writeln!(
+                        tx,
+                        "{:2}: Acceleration [0][mg]:{:4.2}\t{:4.2}\t{:4.2}\tTemp[degC]:{:3.2}",
+                        curr_entry, xl.mg[0], xl.mg[1], xl.mg[2], heat.deg_c
+                    )
+                    .unwrap();
                 }
-                _ => {
+                FifoSample::Accel(xl) => {
                     writeln!(
                         tx,
-                        "unknown TAG ({:#02x})",
-                        FifoSensorTag::try_from(fdata.tag).unwrap_or_default() as u8
+                        "{:2}: Acceleration [0][mg]:{:4.2}\t{:4.2}\t{:4.2}",
+                        curr_entry, xl.mg[0], xl.mg[1], xl.mg[2]
                     )
                     .unwrap();
                 }
+                FifoSample::Timestamp(raw) => {
+                    ts = SensorTime::from_raw(raw).as_millis();
+                    writeln!(tx, "Timestamp:\t{} ms", ts).unwrap();
+                }
+                FifoSample::ConfigChange(cfg_chg) => {
+                    ts = SensorTime::from_raw(cfg_chg.timestamp).as_millis();
+                    writeln!(tx, "Config change:\t{} ms", ts).unwrap();
+                }
+                FifoSample::Unknown { tag, .. } => {
+                    writeln!(tx, "unknown TAG ({:#02x})", tag).unwrap();
+                }
+                _ => {}
             }
         }
 
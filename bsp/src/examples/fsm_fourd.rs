@@ -4,7 +4,7 @@ use crate::*;
 use core::write;
 
 use crate::config::fsm_config::FOURD;
-use st_mems_reg_config_conv::ucf_entry::MemsUcfOp;
+use crate::iis2dulpx::fsm::FsmOutput;
 
 #[repr(u8)]
 enum FourdEvent {
@@ -12,16 +12,16 @@ enum FourdEvent {
     PortraitUp,
     LandscapeRight,
     LandscapeLeft,
-    Unknown(u8),
+    Unknown(FsmOutput),
 }
 
-impl From<u8> for FourdEvent {
-    fn from(value: u8) -> Self {
-        match value {
-            0x10 => Self::PortraitDown,
-            0x20 => Self::PortraitUp,
-            0x40 => Self::LandscapeRight,
-            0x80 => Self::LandscapeLeft,
+impl From<FsmOutput> for FourdEvent {
+    fn from(output: FsmOutput) -> Self {
+        match output {
+            FsmOutput { n_y: 1, .. } => Self::PortraitDown,
+            FsmOutput { p_y: 1, .. } => Self::PortraitUp,
+            FsmOutput { n_x: 1, .. } => Self::LandscapeRight,
+            FsmOutput { p_x: 1, .. } => Self::LandscapeLeft,
             other => Self::Unknown(other),
         }
     }
@@ -34,7 +34,7 @@ impl core::fmt::Display for FourdEvent {
             FourdEvent::PortraitUp => write!(f, "Y-axis pointing up"),
             FourdEvent::LandscapeRight => write!(f, "X-axis pointing down"),
             FourdEvent::LandscapeLeft => write!(f, "X-axis pointing up"),
-            FourdEvent::Unknown(v) => write!(f, "Unkown event: {v}"),
+            FourdEvent::Unknown(v) => write!(f, "Unkown event: {v:?}"),
         }
     }
 }
@@ -68,29 +68,17 @@ where
     // Set BDU and IF_INC recommended for driver usage
     sensor.init_set().await.unwrap();
 
-    for ucf_entry in FOURD {
-        match ucf_entry.op {
-            MemsUcfOp::Delay => {
-                sensor.tim.delay_ms(ucf_entry.data.into()).await;
-            }
-            MemsUcfOp::Write => {
-                sensor
-                    .bus
-                    .write_to_register(ucf_entry.address as u8, &[ucf_entry.data])
-                    .await
-                    .unwrap();
-            }
-            _ => {}
-        }
-    }
+    // Load the FSM configuration
+    sensor.load_reg_config(&FOURD).await.unwrap();
 
     loop {
         // Wait for interrupt
         int_pin.wait_for_event().await;
 
-        let status = sensor.fsm_status_get().await.unwrap();
-        if status.is_fsm1() == 1 {
-            let catched: FourdEvent = sensor.fsm_out_get().await.unwrap()[0].into();
-            writeln!(tx, "{catched}").unwrap();
+        for (index, output) in sensor.fsm_events_get().await.unwrap() {
+            if index == 0 {
+                let catched: FourdEvent = output.into();
+                writeln!(tx, "{catched}").unwrap();
+            }
         }
     }}
@@ -3,7 +3,6 @@ use maybe_async::maybe_async;
 use crate::*;
 
 use crate::config::mlc_config::VIBRATION;
-use st_mems_reg_config_conv::ucf_entry::MemsUcfOp;
 
 #[maybe_async]
 pub async fn run<B, D, L, I>(bus: B, mut tx: L, delay: D, mut int_pin: I) -> !
@@ -34,21 +33,8 @@ where
     // Set BDU and IF_INC recommended for driver usage
     sensor.init_set().await.unwrap();
 
-    for ucf_entry in VIBRATION {
-        match ucf_entry.op {
-            MemsUcfOp::Delay => {
-                sensor.tim.delay_ms(ucf_entry.data.into()).await;
-            }
-            MemsUcfOp::Write => {
-                sensor
-                    .bus
-                    .write_to_register(ucf_entry.address as u8, &[ucf_entry.data])
-                    .await
-                    .unwrap();
-            }
-            _ => {}
-        }
-    }
+    // Load the MLC configuration
+    sensor.load_reg_config(&VIBRATION).await.unwrap();
 
     loop {
         // Wait for interrupt
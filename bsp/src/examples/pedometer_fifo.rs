@@ -14,6 +14,7 @@ where
 {
     use iis2dulpx::*;
     use iis2dulpx::prelude::*;
+    use iis2dulpx::timestamp::SensorTime;
 
     info!("Configuring the sensor");
     let mut sensor = Iis2dulpx::from_bus(bus, delay.clone());
@@ -96,11 +97,9 @@ where
 
             let fdata = sensor.fifo_data_get(&md, &fifo_mode).await.unwrap();
 
-            if let FifoSensorTag::StepCounterTag =
-                FifoSensorTag::try_from(fdata.tag).unwrap_or_default()
-            {
-                let ts = fdata.pedo.timestamp as f32 / 100f32;
-                let steps = fdata.pedo.steps;
+            if let FifoSample::Steps(pedo) = fdata {
+                let ts = SensorTime::from_raw(pedo.timestamp).as_micros() as f32 / 1000f32;
+                let steps = pedo.steps;
 
                 writeln!(tx, "Steps: {:03} ({} ms)", steps, ts).unwrap();
             }
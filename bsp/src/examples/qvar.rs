@@ -32,21 +32,20 @@ where
     // Set BDU and IF_INC recommended for driver usage
     sensor.init_set().await.unwrap();
 
-    // Set Output Data Rate
+    // Set Output Data Rate and AH_QVAR front-end
     let md = Md {
         fs: Fs::_4g,
         bw: Bw::OdrDiv4,
         odr: Odr::_25hzLp,
     };
-    sensor.mode_set(&md).await.unwrap();
-
-    let qvar_mode = AhQvarMode {
-        ah_qvar_en: 1,
+    let qvar_acq = QvarAcquisition {
+        md,
         ah_qvar_zin: AhQvarZin::_520mohm,
         ah_qvar_gain: AhQvarGain::_05,
+        fifo_operation: FifoOperation::FifoOff,
         ..Default::default()
     };
-    sensor.ah_qvar_mode_set(&qvar_mode).await.unwrap();
+    sensor.qvar_acquisition_set(&qvar_acq).await.unwrap();
 
     let int_route = PinInt1Route {
         drdy: 1,
@@ -62,13 +61,12 @@ where
         let status = sensor.status_get().await.unwrap();
 
         if status.drdy == 1 {
-            let data_qvar = sensor.ah_qvar_data_get().await.unwrap();
-            let data_xl = sensor.xl_data_get(&md).await.unwrap();
+            let data = sensor.xl_qvar_data_get(&md).await.unwrap();
 
             writeln!(
                 tx,
                 "Acceleration [mg]:{:4.2}\t{:4.2}\t{:4.2} - QVAR [LSB]: {}",
-                data_xl.mg[0], data_xl.mg[1], data_xl.mg[2], data_qvar.raw
+                data.xl.mg[0], data.xl.mg[1], data.xl.mg[2], data.ah_qvar.raw
             )
             .unwrap();
         }
@@ -41,26 +41,31 @@ async fn st_avg_5_samples<B, T>(
     sensor: &mut Iis2dulpx<B, T, MainBank>,
     md: &Md,
     fmode: &FifoMode,
-) -> Result<FifoData, Error<B::Error>>
+) -> Result<[f32; 3], Error<B::Error>>
 where
     B: BusOperation,
     T: DelayNs,
 {
-    let mut fdata = FifoData::default();
+    let mut avg = [0.0; 3];
+    let mut count = 0;
 
     for _ in 0..5 {
         let tmp = sensor.fifo_data_get(md, fmode).await?;
 
-        for j in 0..3 {
-            fdata.xl[0].mg[j] += tmp.xl[0].mg[j];
+        // records without acceleration (e.g. timestamps) are not averaged
+        if let Some(xl) = tmp.xl().first() {
+            for j in 0..3 {
+                avg[j] += xl.mg[j];
+            }
+            count += 1;
         }
     }
 
     for j in 0..3 {
-        fdata.xl[0].mg[j] /= 5.0;
+        avg[j] /= count.max(1) as f32;
     }
 
-    Ok(fdata)
+    Ok(avg)
 }
 
 #[maybe_async]
@@ -237,9 +242,9 @@ where
              * each axis and verify that it falls within the range provided in the
              * datasheet
              */
-            let mut st_dev = FifoData::default();
+            let mut st_dev = [0.0; 3];
             for i in 0..3 {
-                st_dev.xl[0].mg[i] = 2.0 * fabsf(fdata2.xl[0].mg[i] - fdata1.xl[0].mg[i]);
+                st_dev[i] = 2.0 * fabsf(fdata2[i] - fdata1[i]);
             }
             /*
              * 19. Set device in Power Down mode
@@ -253,12 +258,12 @@ where
 
             /* check if stdev falls into given ranges */
             let mut st_result = StResult::StFail;
-            if (st_dev.xl[0].mg[0] >= ST_RANGE_DEV_X_MIN.into()
-                && st_dev.xl[0].mg[0] <= ST_RANGE_DEV_X_MAX.into())
-                && (st_dev.xl[0].mg[1] >= ST_RANGE_DEV_Y_MIN.into()
-                    && st_dev.xl[0].mg[1] <= ST_RANGE_DEV_Y_MAX.into())
-                && (st_dev.xl[0].mg[2] >= ST_RANGE_DEV_Z_MIN.into()
-                    && st_dev.xl[0].mg[2] <= ST_RANGE_DEV_Z_MAX.into())
+            if (st_dev[0] >= ST_RANGE_DEV_X_MIN.into()
+                && st_dev[0] <= ST_RANGE_DEV_X_MAX.into())
+                && (st_dev[1] >= ST_RANGE_DEV_Y_MIN.into()
+                    && st_dev[1] <= ST_RANGE_DEV_Y_MAX.into())
+                && (st_dev[2] >= ST_RANGE_DEV_Z_MIN.into()
+                    && st_dev[2] <= ST_RANGE_DEV_Z_MAX.into())
             {
                 st_result = StResult::StPass;
             }
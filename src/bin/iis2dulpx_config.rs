@@ -0,0 +1,221 @@
+//! Host-side tool for IIS2DULPX register configuration programs.
+//!
+//! Reads `reg_config` JSON files, as exported by MEMS Studio, or binary containers, and:
+//!
+//! * `list`: prints the program with every write decoded into the register it targets,
+//!   following the bank switches and the advanced page accesses;
+//! * `diff`: compares two programs entry by entry;
+//! * `export rust`: prints the program as a Rust constant for `load_reg_config`;
+//! * `export bin`: writes the program as a binary container for `load_reg_config_bin`.
+//!
+//! Built with the `std` feature:
+//!
+//! ```sh
+//! cargo run --features std --bin iis2dulpx-config -- list config.json
+//! ```
+
+use std::fmt::Write as _;
+use std::process::ExitCode;
+
+#[cfg(feature = "async")]
+use iis2dulpx_rs::asynchronous as iis2dulpx;
+#[cfg(not(feature = "async"))]
+use iis2dulpx_rs::blocking as iis2dulpx;
+
+use iis2dulpx::reg_config::binary::{ConvertError, ProgramType, encode, encoded_len};
+use iis2dulpx::reg_config::export::{read_program, rust_source};
+use iis2dulpx::reg_config::{UcfEntry, UcfOp};
+use iis2dulpx::register::advanced::EmbAdvReg;
+use iis2dulpx::register::embedded::{EmbReg, PageRw, PageSel};
+use iis2dulpx::register::main::{FuncCfgAccess, Reg};
+
+const USAGE: &str = "\
+Usage:
+  iis2dulpx-config list <FILE>
+  iis2dulpx-config diff <FILE_A> <FILE_B>
+  iis2dulpx-config export rust <FILE> <NAME> [REG_CONFIG_PATH]
+  iis2dulpx-config export bin <FILE> <generic|mlc|fsm> <OUTPUT>
+
+FILE is a reg_config JSON file or a binary container.
+REG_CONFIG_PATH defaults to iis2dulpx_rs::asynchronous::reg_config.";
+
+/// Follows the bank and page state of a program to name the register of every write.
+#[derive(Default)]
+struct Annotator {
+    emb_bank: bool,
+    page: u8,
+    page_address: u8,
+    page_write: bool,
+}
+
+impl Annotator {
+    /// Describes one entry, without its index.
+    fn describe(&mut self, entry: &UcfEntry) -> String {
+        let (address, data) = (entry.address, entry.data);
+        if entry.op == UcfOp::Delay {
+            return format!("delay {data} ms");
+        }
+
+        let bank = if self.emb_bank { "emb " } else { "main" };
+        let name = if address == Reg::FuncCfgAccess as u8 {
+            self.emb_bank = FuncCfgAccess::from_bits(data).emb_func_reg_access() != 0;
+            Some(format!("{:?}", Reg::FuncCfgAccess))
+        } else if self.emb_bank {
+            EmbReg::try_from(address).ok().map(|reg| format!("{reg:?}"))
+        } else {
+            Reg::try_from(address).ok().map(|reg| format!("{reg:?}"))
+        };
+        let name = name.unwrap_or_else(|| String::from("?"));
+
+        let mut detail = String::new();
+        if self.emb_bank && address != Reg::FuncCfgAccess as u8 {
+            match EmbReg::try_from(address) {
+                Ok(EmbReg::PageSel) => {
+                    self.page = PageSel::from_bits(data).page_sel();
+                    detail = format!("page {}", self.page);
+                }
+                Ok(EmbReg::PageAddress) => self.page_address = data,
+                Ok(EmbReg::PageRw) => {
+                    self.page_write = PageRw::from_bits(data).page_write() != 0;
+                    detail = String::from(if self.page_write { "page write" } else { "" });
+                }
+                Ok(EmbReg::PageValue) if self.page_write => {
+                    detail = self.page_target();
+                    self.page_address = self.page_address.wrapping_add(1);
+                }
+                _ => {}
+            }
+        }
+
+        let mut text = format!("{bank}  0x{address:02X}  {name:<22} 0x{data:02X}");
+        if !detail.is_empty() {
+            let _ = write!(text, "  ({detail})");
+        }
+
+        text
+    }
+
+    /// Names the advanced page location written by `PAGE_VALUE`.
+    fn page_target(&self) -> String {
+        let location = ((self.page as u16) << 8) | self.page_address as u16;
+        let name = EmbAdvReg::try_from(self.page_address)
+            .ok()
+            .filter(|_| self.page == 0);
+
+        match name {
+            Some(reg) => format!("-> 0x{location:03X} {reg:?}"),
+            None => format!("-> 0x{location:03X}"),
+        }
+    }
+}
+
+fn describe_all(config: &[UcfEntry]) -> Vec<String> {
+    let mut annotator = Annotator::default();
+    config
+        .iter()
+        .map(|entry| annotator.describe(entry))
+        .collect()
+}
+
+fn load(path: &str) -> Result<Vec<UcfEntry>, String> {
+    let bytes = std::fs::read(path).map_err(|err| format!("{path}: {err}"))?;
+
+    read_program(&bytes).map_err(|err| match err {
+        ConvertError::Json(err) => format!("{path}: invalid reg_config JSON: {err:?}"),
+        ConvertError::Bin(err) => format!("{path}: invalid binary container: {err:?}"),
+    })
+}
+
+fn list(path: &str) -> Result<bool, String> {
+    let config = load(path)?;
+    for (index, line) in describe_all(&config).iter().enumerate() {
+        println!("{index:4}  {line}");
+    }
+
+    Ok(true)
+}
+
+/// Prints the entries that differ, using a longest common subsequence of the decoded lines.
+fn diff(path_a: &str, path_b: &str) -> Result<bool, String> {
+    let a = describe_all(&load(path_a)?);
+    let b = describe_all(&load(path_b)?);
+
+    // lcs[i][j] is the length of the longest common subsequence of a[i..] and b[j..].
+    let mut lcs = vec![vec![0u32; b.len() + 1]; a.len() + 1];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            lcs[i][j] = if a[i] == b[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    println!("--- {path_a}");
+    println!("+++ {path_b}");
+    let (mut i, mut j, mut same) = (0, 0, true);
+    while i < a.len() || j < b.len() {
+        if i < a.len() && j < b.len() && a[i] == b[j] {
+            i += 1;
+            j += 1;
+        } else if j == b.len() || (i < a.len() && lcs[i + 1][j] >= lcs[i][j + 1]) {
+            println!("-{i:4}  {}", a[i]);
+            i += 1;
+            same = false;
+        } else {
+            println!("+{j:4}  {}", b[j]);
+            j += 1;
+            same = false;
+        }
+    }
+
+    Ok(same)
+}
+
+fn export(args: &[String]) -> Result<bool, String> {
+    match args {
+        [format, path, name, rest @ ..] if format == "rust" && rest.len() <= 1 => {
+            let reg_config_path = rest
+                .first()
+                .map_or("iis2dulpx_rs::asynchronous::reg_config", String::as_str);
+            print!("{}", rust_source(name, reg_config_path, &load(path)?));
+            Ok(true)
+        }
+        [format, path, program_type, output] if format == "bin" => {
+            let program_type = match program_type.as_str() {
+                "generic" => ProgramType::Generic,
+                "mlc" => ProgramType::Mlc,
+                "fsm" => ProgramType::Fsm,
+                other => return Err(format!("unknown program type `{other}`")),
+            };
+            let config = load(path)?;
+            let mut bin = vec![0; encoded_len(config.len())];
+            encode(&config, program_type, &mut bin).map_err(|err| format!("{path}: {err:?}"))?;
+            std::fs::write(output, &bin).map_err(|err| format!("{output}: {err}"))?;
+            Ok(true)
+        }
+        _ => Err(String::from(USAGE)),
+    }
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+
+    let result = match args.as_slice() {
+        [command, path] if command == "list" => list(path),
+        [command, path_a, path_b] if command == "diff" => diff(path_a, path_b),
+        [command, rest @ ..] if command == "export" => export(rest),
+        _ => Err(String::from(USAGE)),
+    };
+
+    match result {
+        Ok(true) => ExitCode::SUCCESS,
+        // Programs differ.
+        Ok(false) => ExitCode::from(1),
+        Err(err) => {
+            eprintln!("error: {err}");
+            ExitCode::from(2)
+        }
+    }
+}
@@ -0,0 +1,344 @@
+//! Pre-trigger event capture.
+//!
+//! The FIFO trigger modes keep a window of acceleration around an event routed to an
+//! interrupt pin (wake-up, tap, free-fall, ...):
+//!
+//! * `StreamToFifoMode`: the FIFO runs in stream mode, continuously overwriting the oldest
+//!   entries, and switches to FIFO mode on the event. It then stops when full, so it holds
+//!   samples from before and after the event.
+//! * `BypassToStreamMode`: the FIFO stays empty until the event and then runs in stream
+//!   mode, so it only holds samples from after the event.
+//!
+//! The driver arms the capture with `event_capture_arm`, waits for the event with
+//! `event_capture_wait`, drains the FIFO with `event_capture_read` and restores the previous
+//! settings with `event_capture_disarm`. [`EventCapture`] holds the state shared by these calls
+//! and places every sample relative to the event time.
+
+use super::fifo::FifoDecoder;
+use super::register::main::{AllSources, DecTs, FifoOperation, FifoRecord, Xl};
+use super::timestamp::{SensorTime, TIMESTAMP_TICK_US};
+
+/// Represents the FIFO trigger modes usable for event capture.
+///
+/// # Variants
+///
+/// - `StreamToFifo`: Keeps pre-trigger samples and fills the rest of the FIFO after the event.
+/// - `BypassToStream`: Keeps post-trigger samples only, the FIFO streams after the event.
+#[derive(Clone, Copy, PartialEq, Default, Debug)]
+pub enum CaptureTrigger {
+    #[default]
+    StreamToFifo,
+    BypassToStream,
+}
+
+impl CaptureTrigger {
+    /// Returns the FIFO operation mode implementing the trigger.
+    pub fn operation(&self) -> FifoOperation {
+        match self {
+            CaptureTrigger::StreamToFifo => FifoOperation::StreamToFifoMode,
+            CaptureTrigger::BypassToStream => FifoOperation::BypassToStreamMode,
+        }
+    }
+}
+
+/// Represents the event expected by an [`EventCapture`].
+///
+/// # Variants
+///
+/// - `WakeUp`: Wake-up event.
+/// - `FreeFall`: Free-fall event.
+/// - `SingleTap`: Single tap event.
+/// - `DoubleTap`: Double tap event.
+/// - `TripleTap`: Triple tap event.
+/// - `SixD`: 6D orientation change.
+///
+/// # Description
+///
+/// The event must be configured and routed to INT1 or INT2 beforehand, it is also the event
+/// that switches the FIFO mode.
+#[derive(Clone, Copy, PartialEq, Default, Debug)]
+pub enum CaptureEvent {
+    #[default]
+    WakeUp,
+    FreeFall,
+    SingleTap,
+    DoubleTap,
+    TripleTap,
+    SixD,
+}
+
+impl CaptureEvent {
+    /// Returns `true` if the event is flagged in `sources`.
+    pub fn is_set(&self, sources: &AllSources) -> bool {
+        let flag = match self {
+            CaptureEvent::WakeUp => sources.wake_up,
+            CaptureEvent::FreeFall => sources.free_fall,
+            CaptureEvent::SingleTap => sources.single_tap,
+            CaptureEvent::DoubleTap => sources.double_tap,
+            CaptureEvent::TripleTap => sources.triple_tap,
+            CaptureEvent::SixD => sources.six_d,
+        };
+
+        flag != 0
+    }
+}
+
+/// Represents an accelerometer sample read back from an event capture.
+///
+/// # Fields
+///
+/// - `xl: Xl`: The acceleration.
+/// - `offset_us: Option<i64>`: Time of the sample relative to the event in microseconds,
+///   negative before the event. `None` until a timestamp record has been read.
+#[derive(Clone, Copy, PartialEq, Default, Debug)]
+pub struct CapturedSample {
+    pub xl: Xl,
+    pub offset_us: Option<i64>,
+}
+
+impl CapturedSample {
+    /// Returns `true` if the sample was acquired before the event.
+    pub fn is_pre_trigger(&self) -> bool {
+        self.offset_us.is_some_and(|offset| offset < 0)
+    }
+}
+
+/// Settings modified by `event_capture_arm` and restored by `event_capture_disarm`.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub(crate) struct CaptureRestore {
+    pub(crate) dec_ts: DecTs,
+    pub(crate) timestamp: u8,
+    pub(crate) operation: FifoOperation,
+}
+
+/// Tracks an event capture from arming to readout.
+///
+/// # Description
+///
+/// Created by `event_capture_arm`, which also enables timestamp batching for every sample so
+/// that each FIFO entry can be placed in time. The event time is taken from the timestamp
+/// register when `event_capture_wait` sees the event flag, so it lags the actual event by the
+/// polling latency (about 1 ms plus the bus transfers).
+#[derive(Clone, Copy)]
+pub struct EventCapture {
+    trigger: CaptureTrigger,
+    event: CaptureEvent,
+    decoder: FifoDecoder,
+    event_raw: Option<u32>,
+    pending: Option<CapturedSample>,
+    restore: Option<CaptureRestore>,
+}
+
+impl EventCapture {
+    /// Creates a capture decoding records with `decoder`.
+    pub fn new(trigger: CaptureTrigger, event: CaptureEvent, decoder: FifoDecoder) -> Self {
+        Self {
+            trigger,
+            event,
+            decoder,
+            event_raw: None,
+            pending: None,
+            restore: None,
+        }
+    }
+
+    /// Stores the settings to restore when the capture is disarmed.
+    pub(crate) fn with_restore(self, restore: CaptureRestore) -> Self {
+        Self {
+            restore: Some(restore),
+            ..self
+        }
+    }
+
+    /// Returns the settings to restore when the capture is disarmed.
+    pub(crate) fn restore(&self) -> Option<CaptureRestore> {
+        self.restore
+    }
+
+    /// Returns the trigger mode.
+    pub fn trigger(&self) -> CaptureTrigger {
+        self.trigger
+    }
+
+    /// Returns the expected event.
+    pub fn event(&self) -> CaptureEvent {
+        self.event
+    }
+
+    /// Records the raw timestamp of the event.
+    pub fn set_event_time(&mut self, raw: u32) {
+        self.event_raw = Some(raw);
+    }
+
+    /// Returns the time of the event, if it has occurred.
+    pub fn event_time(&self) -> Option<SensorTime> {
+        self.event_raw.map(SensorTime::from_raw)
+    }
+
+    /// Returns `true` once the event has been seen.
+    pub fn is_triggered(&self) -> bool {
+        self.event_raw.is_some()
+    }
+
+    /// Decodes a record read from the FIFO and appends its accelerometer samples to `out`.
+    ///
+    /// # Returns
+    ///
+    /// * `usize`: The number of samples written to `out`, at most 2. A sample that does not
+    ///   fit is kept and returned by [`EventCapture::take_pending`]; if a sample is already
+    ///   pending, further samples that do not fit are dropped.
+    pub fn push(&mut self, record: &FifoRecord, out: &mut [CapturedSample]) -> usize {
+        let timed = self.decoder.decode_timed(record);
+
+        let mut count = 0;
+        for (xl, time) in timed.sample.xl().iter().zip(timed.time) {
+            let sample = CapturedSample {
+                xl: *xl,
+                offset_us: time.and_then(|time| self.offset_us(time)),
+            };
+            match out.get_mut(count) {
+                Some(slot) => {
+                    *slot = sample;
+                    count += 1;
+                }
+                None => {
+                    self.pending.get_or_insert(sample);
+                }
+            }
+        }
+
+        count
+    }
+
+    /// Returns the sample that did not fit in the output of the last [`EventCapture::push`].
+    pub fn take_pending(&mut self) -> Option<CapturedSample> {
+        self.pending.take()
+    }
+
+    /// Returns the time from the event to `time` in microseconds.
+    ///
+    /// The difference is computed on the low 32 bits, so it is correct across one counter
+    /// rollover.
+    fn offset_us(&self, time: SensorTime) -> Option<i64> {
+        let ticks = (time.0 as u32).wrapping_sub(self.event_raw?) as i32;
+
+        Some(ticks as i64 * TIMESTAMP_TICK_US as i64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::register::main::{Batch, BdrXl, Bw, FifoMode, FifoSensorTag, Fs, Md, Odr};
+    use super::*;
+
+    fn ts_record(raw: u32) -> FifoRecord {
+        let b = raw.to_le_bytes();
+        FifoRecord {
+            tag: FifoSensorTag::TimestampTag as u8,
+            data: [0, 0, b[0], b[1], b[2], b[3]],
+        }
+    }
+
+    const XL_RECORD: FifoRecord = FifoRecord {
+        tag: FifoSensorTag::XlTempTag as u8,
+        data: [0; 6],
+    };
+
+    const PAIR_RECORD: FifoRecord = FifoRecord {
+        tag: FifoSensorTag::XlOnly2xTag as u8,
+        data: [0; 6],
+    };
+
+    /// 50 Hz batching: 2000 timestamp ticks between samples.
+    fn capture() -> EventCapture {
+        let md = Md {
+            odr: Odr::_100hzLp,
+            fs: Fs::_2g,
+            bw: Bw::OdrDiv2,
+        };
+        let f_md = FifoMode {
+            xl_only: 1,
+            ..Default::default()
+        };
+        let batch = Batch {
+            bdr_xl: BdrXl::OdrDiv2,
+            ..Default::default()
+        };
+        EventCapture::new(
+            CaptureTrigger::StreamToFifo,
+            CaptureEvent::WakeUp,
+            FifoDecoder::new(&md, &f_md, &batch),
+        )
+    }
+
+    fn offsets(capture: &mut EventCapture, records: &[FifoRecord]) -> [Option<i64>; 4] {
+        let mut out = [CapturedSample::default(); 4];
+        let mut count = 0;
+        for record in records {
+            count += capture.push(record, &mut out[count..]);
+        }
+        out.map(|sample| sample.offset_us)
+    }
+
+    #[test]
+    fn offset_around_event() {
+        let mut capture = capture();
+        assert!(!capture.is_triggered());
+        capture.set_event_time(5000);
+        assert!(capture.is_triggered());
+        assert_eq!(capture.event_time(), Some(SensorTime(5000)));
+
+        let records = [ts_record(1000), XL_RECORD, XL_RECORD, XL_RECORD, XL_RECORD];
+        assert_eq!(
+            offsets(&mut capture, &records),
+            [Some(-40_000), Some(-20_000), Some(0), Some(20_000)]
+        );
+    }
+
+    #[test]
+    fn offset_without_event_or_timestamp() {
+        let mut capture = capture();
+        let records = [XL_RECORD, ts_record(1000), XL_RECORD];
+        assert_eq!(offsets(&mut capture, &records), [None; 4]);
+
+        let mut sample = CapturedSample::default();
+        assert_eq!(
+            capture.push(&XL_RECORD, core::slice::from_mut(&mut sample)),
+            1
+        );
+        assert!(!sample.is_pre_trigger());
+    }
+
+    #[test]
+    fn offset_across_rollover() {
+        let mut capture = capture();
+        capture.set_event_time(u32::MAX - 499);
+
+        let records = [ts_record(u32::MAX - 999), XL_RECORD, XL_RECORD];
+        assert_eq!(
+            offsets(&mut capture, &records),
+            [Some(-5000), Some(15_000), None, None]
+        );
+    }
+
+    #[test]
+    fn push_keeps_overflow() {
+        let mut capture = capture();
+        capture.set_event_time(0);
+        let mut out = [CapturedSample::default(); 1];
+
+        assert_eq!(capture.push(&ts_record(0), &mut out), 0);
+        assert_eq!(capture.take_pending(), None);
+
+        assert_eq!(capture.push(&PAIR_RECORD, &mut out), 1);
+        assert_eq!(out[0].offset_us, Some(0));
+        let pending = capture.take_pending().unwrap();
+        assert_eq!(pending.offset_us, Some(20_000));
+        assert_eq!(capture.take_pending(), None);
+
+        // only one sample is kept when nothing fits
+        assert_eq!(capture.push(&PAIR_RECORD, &mut []), 0);
+        assert_eq!(capture.take_pending().unwrap().offset_us, Some(40_000));
+        assert_eq!(capture.take_pending(), None);
+    }
+}
@@ -1,6 +1,18 @@
+#[cfg(feature = "heapless")]
+use super::queue::FifoProducer;
+#[cfg(feature = "reg_config_json")]
+use super::reg_config::json::JsonProgram;
 use super::{
     BusOperation, DelayNs, EmbAdvFunctions, I2c, MemBankFunctions, RegisterOperation,
-    SensorOperation, SevenBitAddress, SpiDevice, bisync, i2c, prelude::*, register::BankState, spi,
+    SensorOperation, SevenBitAddress, SpiDevice, bisync,
+    capture::{CaptureEvent, CaptureRestore, CaptureTrigger, CapturedSample, EventCapture},
+    fsm::{FSM_START_ADDRESS, FsmEvents, FsmLayout, FsmLayoutError, FsmOutput, FsmProgram},
+    i2c,
+    prelude::*,
+    reg_config::{UcfEntry, UcfOp, binary::BinProgram},
+    register::BankState,
+    spi,
+    timestamp::SensorTime,
 };
 
 use core::fmt::Debug;
@@ -32,10 +44,15 @@ pub enum Error<B> {
     FailedToBoot,
     FailedToSwReset,
     InvalidBwForODR,
+    InvalidOdrForQvar,
+    EmbFuncNotEnabled,
+    RegConfigEndsInEmbBank,
+    InvalidRegConfig,
     InvalidValue,
     BufferTooSmall,
     FailedToReadMemBank,
     FailedToSetMembank(MemBank),
+    FsmLayout(FsmLayoutError),
 }
 
 impl<B, T, S> Iis2dulpx<B, T, S>
@@ -594,6 +611,127 @@ impl<B: BusOperation, T: DelayNs> Iis2dulpx<B, T, MainBank> {
         Ok(val)
     }
 
+    /// Loads a register configuration program, such as an MLC or FSM configuration.
+    ///
+    /// # Arguments
+    ///
+    /// - `config: &[UcfEntry]`: The program, in execution order.
+    ///
+    /// # Returns
+    ///
+    /// - `Result<(), Error<B::Error>>`:
+    ///   - `Ok`: Indicates that the program has been loaded.
+    ///   - `Err`: Returns an error if the operation fails. Possible error variants include:
+    ///     - `Error::Bus`: Indicates an error at the bus level.
+    ///     - `Error::RegConfigEndsInEmbBank`: The program left the embedded functions bank
+    ///       selected. The main bank has been restored.
+    ///
+    /// # Description
+    ///
+    /// Writes are issued as raw single-register writes and delays use the driver timer. Writes
+    /// to `FUNC_CFG_ACCESS` are mirrored in `func_cfg_access_main`, so that the bank tracking
+    /// of the driver stays consistent with the device. At the end, `FUNC_CFG_ACCESS` is read
+    /// back to check that the device is in the main bank.
+    pub async fn load_reg_config(&mut self, config: &[UcfEntry]) -> Result<(), Error<B::Error>> {
+        for entry in config {
+            self.reg_config_apply(entry).await?;
+        }
+
+        self.reg_config_finish().await
+    }
+
+    /// Loads a register configuration program from its JSON export.
+    ///
+    /// # Arguments
+    ///
+    /// - `json: &[u8]`: The content of a `reg_config` version 2.0 JSON file.
+    ///
+    /// # Returns
+    ///
+    /// - `Result<(), Error<B::Error>>`:
+    ///   - `Ok`: Indicates that the program has been loaded.
+    ///   - `Err`: Returns an error if the operation fails. Possible error variants include:
+    ///     - `Error::Bus`: Indicates an error at the bus level.
+    ///     - `Error::InvalidRegConfig`: The file is malformed, is not a `reg_config` version
+    ///       2.0 file or has no configuration for the IIS2DULPX. Nothing has been written.
+    ///     - `Error::RegConfigEndsInEmbBank`: The program left the embedded functions bank
+    ///       selected. The main bank has been restored.
+    ///
+    /// # Description
+    ///
+    /// The whole file is validated with [`JsonProgram`] before the first write, then the
+    /// entries are decoded and applied one at a time as in `load_reg_config`, without being
+    /// stored. Use [`JsonProgram::parse`] directly to get the position of a parse error.
+    #[cfg(feature = "reg_config_json")]
+    pub async fn load_reg_config_json(&mut self, json: &[u8]) -> Result<(), Error<B::Error>> {
+        let program = JsonProgram::parse(json).map_err(|_| Error::InvalidRegConfig)?;
+        program.validate().map_err(|_| Error::InvalidRegConfig)?;
+
+        for entry in program.entries() {
+            let entry = entry.map_err(|_| Error::InvalidRegConfig)?;
+            self.reg_config_apply(&entry).await?;
+        }
+
+        self.reg_config_finish().await
+    }
+
+    /// Loads a register configuration program stored in the binary container format.
+    ///
+    /// # Arguments
+    ///
+    /// - `bin: &[u8]`: The container, see [`BinProgram`].
+    ///
+    /// # Returns
+    ///
+    /// - `Result<(), Error<B::Error>>`:
+    ///   - `Ok`: Indicates that the program has been loaded.
+    ///   - `Err`: Returns an error if the operation fails. Possible error variants include:
+    ///     - `Error::Bus`: Indicates an error at the bus level.
+    ///     - `Error::InvalidRegConfig`: The header or the CRC is invalid, or the program
+    ///       targets another device. Nothing has been written.
+    ///     - `Error::RegConfigEndsInEmbBank`: The program left the embedded functions bank
+    ///       selected. The main bank has been restored.
+    ///
+    /// # Description
+    ///
+    /// The container is fully validated before the first write, then the entries are applied
+    /// as in `load_reg_config`. Use [`BinProgram::parse`] directly to know which check failed.
+    pub async fn load_reg_config_bin(&mut self, bin: &[u8]) -> Result<(), Error<B::Error>> {
+        let program = BinProgram::parse(bin).map_err(|_| Error::InvalidRegConfig)?;
+
+        for entry in program.entries() {
+            self.reg_config_apply(&entry).await?;
+        }
+
+        self.reg_config_finish().await
+    }
+
+    /// Applies one entry of a register configuration program.
+    async fn reg_config_apply(&mut self, entry: &UcfEntry) -> Result<(), Error<B::Error>> {
+        match entry.op {
+            UcfOp::Delay => self.tim.delay_ms(entry.data as u32).await,
+            UcfOp::Write => {
+                self.write_to_register(entry.address, &[entry.data]).await?;
+                if entry.address == Reg::FuncCfgAccess as u8 {
+                    self.func_cfg_access_main = FuncCfgAccess::from_bits(entry.data);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Checks that a register configuration program has returned to the main bank.
+    async fn reg_config_finish(&mut self) -> Result<(), Error<B::Error>> {
+        self.func_cfg_access_main = FuncCfgAccess::read(self).await?;
+        if self.func_cfg_access_main.emb_func_reg_access() != 0 {
+            self.mem_bank_set(MemBank::MainMemBank).await?;
+            return Err(Error::RegConfigEndsInEmbBank);
+        }
+
+        Ok(())
+    }
+
     /// FSM capability to write CTRL regs.
     ///
     /// # Arguments
@@ -1529,6 +1667,7 @@ impl<B: BusOperation, T: DelayNs> Iis2dulpx<B, T, MainBank> {
     ///
     /// This function configures the routing of embedded function interrupt signals on the INT1 pin by modifying
     /// the `EmbFuncInt1` register. It ensures the correct memory bank is set before and after the operation.
+    /// The FSM and MLC interrupts are routed with `fsm_int1_route_set` and `mlc_int1_route_set`.
     pub async fn emb_pin_int1_route_set(
         &mut self,
         val: &EmbPinIntRoute,
@@ -1561,6 +1700,7 @@ impl<B: BusOperation, T: DelayNs> Iis2dulpx<B, T, MainBank> {
     ///
     /// This function reads the `EmbFuncInt1` register to retrieve the current embedded function interrupt signals
     /// routing configuration on the INT1 pin. It ensures the correct memory bank is set before and after the operation.
+    /// The FSM and MLC routing is read with `fsm_int1_route_get` and `mlc_int1_route_get`.
     pub async fn emb_pin_int1_route_get(&mut self) -> Result<EmbPinIntRoute, Error<B::Error>> {
         self.operate_over_emb(async |state| {
             let emb_func_int1 = EmbFuncInt1::read(state).await?;
@@ -1662,6 +1802,7 @@ impl<B: BusOperation, T: DelayNs> Iis2dulpx<B, T, MainBank> {
     ///
     /// This function configures the routing of embedded function interrupt signals on the INT2 pin by modifying
     /// the `EmbFuncInt2` register. It ensures the correct memory bank is set before and after the operation.
+    /// The FSM and MLC interrupts are routed with `fsm_int2_route_set` and `mlc_int2_route_set`.
     pub async fn emb_pin_int2_route_set(
         &mut self,
         val: &EmbPinIntRoute,
@@ -1696,6 +1837,7 @@ impl<B: BusOperation, T: DelayNs> Iis2dulpx<B, T, MainBank> {
     ///
     /// This function reads the `EmbFuncInt2` register to retrieve the current embedded function interrupt signals
     /// routing configuration on the INT2 pin. It ensures the correct memory bank is set before and after the operation.
+    /// The FSM and MLC routing is read with `fsm_int2_route_get` and `mlc_int2_route_get`.
     pub async fn emb_pin_int2_route_get(&mut self) -> Result<EmbPinIntRoute, Error<B::Error>> {
         self.operate_over_emb(async |state| {
             let emb_func_int2 = EmbFuncInt2::read(state).await?;
@@ -1710,6 +1852,216 @@ impl<B: BusOperation, T: DelayNs> Iis2dulpx<B, T, MainBank> {
         .await
     }
 
+    /// Routes the FSM program interrupts on the INT1 pin.
+    ///
+    /// # Arguments
+    ///
+    /// - `val: u8`: One bit per program, bit `n` for FSM`n + 1`; `FsmHandle::mask` gives the bit of a
+    ///   loaded program.
+    ///
+    /// # Returns
+    ///
+    /// - `Result<(), Error<B::Error>>`:
+    ///   - `Ok`: Indicates successful configuration.
+    ///   - `Err`: Returns an error if the operation fails. Possible error variants include:
+    ///     - `Error::Bus`: Indicates an error at the bus level.
+    ///
+    /// # Description
+    ///
+    /// This function writes the `FsmInt1` register, replacing the previous routing of all the FSM
+    /// programs. When at least one program is routed, the embedded function interrupt is also
+    /// enabled on the pin in `Md1Cfg`; it is left untouched otherwise, as other embedded functions
+    /// may use it.
+    pub async fn fsm_int1_route_set(&mut self, val: u8) -> Result<(), Error<B::Error>> {
+        self.operate_over_emb(async |state| FsmInt1::from_bits(val).write(state).await)
+            .await?;
+
+        if val != 0 {
+            let mut md_cfg = Md1Cfg::read(self).await?;
+            md_cfg.set_int1_emb_func(1);
+            md_cfg.write(self).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Retrieves the FSM programs routed on the INT1 pin.
+    ///
+    /// # Returns
+    ///
+    /// - `Result<u8, Error<B::Error>>`:
+    ///   - `u8`: One bit per program, bit `n` for FSM`n + 1`; `FsmHandle::mask` gives the bit of a
+    ///     loaded program.
+    ///   - `Err`: Returns an error if the operation fails. Possible error variants include:
+    ///     - `Error::Bus`: Indicates an error at the bus level.
+    ///
+    /// # Description
+    ///
+    /// This function reads the `FsmInt1` register.
+    pub async fn fsm_int1_route_get(&mut self) -> Result<u8, Error<B::Error>> {
+        self.operate_over_emb(async |state| Ok(FsmInt1::read(state).await?.into_bits()))
+            .await
+    }
+
+    /// Routes the MLC decision tree interrupts on the INT1 pin.
+    ///
+    /// # Arguments
+    ///
+    /// - `val: u8`: One bit per decision tree, bit `n` for MLC`n + 1` (bits 0 to 3).
+    ///
+    /// # Returns
+    ///
+    /// - `Result<(), Error<B::Error>>`:
+    ///   - `Ok`: Indicates successful configuration.
+    ///   - `Err`: Returns an error if the operation fails. Possible error variants include:
+    ///     - `Error::Bus`: Indicates an error at the bus level.
+    ///     - `Error::InvalidValue`: `val` selects a tree above MLC4.
+    ///
+    /// # Description
+    ///
+    /// This function writes the `MlcInt1` register, replacing the previous routing of all the MLC
+    /// decision trees. When at least one tree is routed, the embedded function interrupt is also
+    /// enabled on the pin in `Md1Cfg`; it is left untouched otherwise, as other embedded functions
+    /// may use it.
+    pub async fn mlc_int1_route_set(&mut self, val: u8) -> Result<(), Error<B::Error>> {
+        if val > 0x0F {
+            return Err(Error::InvalidValue);
+        }
+
+        self.operate_over_emb(async |state| MlcInt1::from_bits(val).write(state).await)
+            .await?;
+
+        if val != 0 {
+            let mut md_cfg = Md1Cfg::read(self).await?;
+            md_cfg.set_int1_emb_func(1);
+            md_cfg.write(self).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Retrieves the MLC decision trees routed on the INT1 pin.
+    ///
+    /// # Returns
+    ///
+    /// - `Result<u8, Error<B::Error>>`:
+    ///   - `u8`: One bit per decision tree, bit `n` for MLC`n + 1` (bits 0 to 3).
+    ///   - `Err`: Returns an error if the operation fails. Possible error variants include:
+    ///     - `Error::Bus`: Indicates an error at the bus level.
+    ///
+    /// # Description
+    ///
+    /// This function reads the `MlcInt1` register.
+    pub async fn mlc_int1_route_get(&mut self) -> Result<u8, Error<B::Error>> {
+        self.operate_over_emb(async |state| Ok(MlcInt1::read(state).await?.into_bits() & 0x0F))
+            .await
+    }
+
+    /// Routes the FSM program interrupts on the INT2 pin.
+    ///
+    /// # Arguments
+    ///
+    /// - `val: u8`: One bit per program, bit `n` for FSM`n + 1`; `FsmHandle::mask` gives the bit of a
+    ///   loaded program.
+    ///
+    /// # Returns
+    ///
+    /// - `Result<(), Error<B::Error>>`:
+    ///   - `Ok`: Indicates successful configuration.
+    ///   - `Err`: Returns an error if the operation fails. Possible error variants include:
+    ///     - `Error::Bus`: Indicates an error at the bus level.
+    ///
+    /// # Description
+    ///
+    /// This function writes the `FsmInt2` register, replacing the previous routing of all the FSM
+    /// programs. When at least one program is routed, the embedded function interrupt is also
+    /// enabled on the pin in `Md2Cfg`; it is left untouched otherwise, as other embedded functions
+    /// may use it.
+    pub async fn fsm_int2_route_set(&mut self, val: u8) -> Result<(), Error<B::Error>> {
+        self.operate_over_emb(async |state| FsmInt2::from_bits(val).write(state).await)
+            .await?;
+
+        if val != 0 {
+            let mut md_cfg = Md2Cfg::read(self).await?;
+            md_cfg.set_int2_emb_func(1);
+            md_cfg.write(self).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Retrieves the FSM programs routed on the INT2 pin.
+    ///
+    /// # Returns
+    ///
+    /// - `Result<u8, Error<B::Error>>`:
+    ///   - `u8`: One bit per program, bit `n` for FSM`n + 1`; `FsmHandle::mask` gives the bit of a
+    ///     loaded program.
+    ///   - `Err`: Returns an error if the operation fails. Possible error variants include:
+    ///     - `Error::Bus`: Indicates an error at the bus level.
+    ///
+    /// # Description
+    ///
+    /// This function reads the `FsmInt2` register.
+    pub async fn fsm_int2_route_get(&mut self) -> Result<u8, Error<B::Error>> {
+        self.operate_over_emb(async |state| Ok(FsmInt2::read(state).await?.into_bits()))
+            .await
+    }
+
+    /// Routes the MLC decision tree interrupts on the INT2 pin.
+    ///
+    /// # Arguments
+    ///
+    /// - `val: u8`: One bit per decision tree, bit `n` for MLC`n + 1` (bits 0 to 3).
+    ///
+    /// # Returns
+    ///
+    /// - `Result<(), Error<B::Error>>`:
+    ///   - `Ok`: Indicates successful configuration.
+    ///   - `Err`: Returns an error if the operation fails. Possible error variants include:
+    ///     - `Error::Bus`: Indicates an error at the bus level.
+    ///     - `Error::InvalidValue`: `val` selects a tree above MLC4.
+    ///
+    /// # Description
+    ///
+    /// This function writes the `MlcInt2` register, replacing the previous routing of all the MLC
+    /// decision trees. When at least one tree is routed, the embedded function interrupt is also
+    /// enabled on the pin in `Md2Cfg`; it is left untouched otherwise, as other embedded functions
+    /// may use it.
+    pub async fn mlc_int2_route_set(&mut self, val: u8) -> Result<(), Error<B::Error>> {
+        if val > 0x0F {
+            return Err(Error::InvalidValue);
+        }
+
+        self.operate_over_emb(async |state| MlcInt2::from_bits(val).write(state).await)
+            .await?;
+
+        if val != 0 {
+            let mut md_cfg = Md2Cfg::read(self).await?;
+            md_cfg.set_int2_emb_func(1);
+            md_cfg.write(self).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Retrieves the MLC decision trees routed on the INT2 pin.
+    ///
+    /// # Returns
+    ///
+    /// - `Result<u8, Error<B::Error>>`:
+    ///   - `u8`: One bit per decision tree, bit `n` for MLC`n + 1` (bits 0 to 3).
+    ///   - `Err`: Returns an error if the operation fails. Possible error variants include:
+    ///     - `Error::Bus`: Indicates an error at the bus level.
+    ///
+    /// # Description
+    ///
+    /// This function reads the `MlcInt2` register.
+    pub async fn mlc_int2_route_get(&mut self) -> Result<u8, Error<B::Error>> {
+        self.operate_over_emb(async |state| Ok(MlcInt2::read(state).await?.into_bits() & 0x0F))
+            .await
+    }
+
     /// Sets the interrupt configuration mode.
     ///
     /// # Arguments
@@ -2083,6 +2435,340 @@ impl<B: BusOperation, T: DelayNs> Iis2dulpx<B, T, MainBank> {
         Ok(FifoStatus1::read(self).await?.fifo_wtm_ia())
     }
 
+    /// Retrieves the FIFO level and status flags.
+    ///
+    /// # Returns
+    ///
+    /// - `Result<FifoStatus, Error<B::Error>>`:
+    ///   - `FifoStatus`: The FIFO level with the watermark, full and overrun flags.
+    ///   - `Err`: Returns an error if the operation fails. Possible error variants include:
+    ///     - `Error::Bus`: Indicates an error at the bus level.
+    ///
+    /// # Description
+    ///
+    /// The `FifoStatus1` and `FifoStatus2` registers are read in a single 2-byte burst, so the
+    /// flags are consistent with the level. The sensor has no full flag: `full` is derived from
+    /// the level reaching `FIFO_DEPTH`.
+    pub async fn fifo_status_get(&mut self) -> Result<FifoStatus, Error<B::Error>> {
+        let mut buff: [u8; 2] = [0; 2];
+        self.read_from_register(Reg::FifoStatus1 as u8, &mut buff)
+            .await?;
+
+        let status1 = FifoStatus1::from_bits(buff[0]);
+        let level = FifoStatus2::from_bits(buff[1]).fss();
+
+        Ok(FifoStatus {
+            level,
+            wtm: status1.fifo_wtm_ia(),
+            full: (level >= FIFO_DEPTH) as u8,
+            ovr: status1.fifo_ovr_ia(),
+        })
+    }
+
+    /// Flushes the FIFO and restarts it with the current configuration.
+    ///
+    /// # Returns
+    ///
+    /// - `Result<FifoStatus, Error<B::Error>>`:
+    ///   - `FifoStatus`: The FIFO status read just before the flush. `level` is the number of
+    ///     unread entries discarded and `ovr` tells whether an overrun had occurred.
+    ///   - `Err`: Returns an error if the operation fails. Possible error variants include:
+    ///     - `Error::Bus`: Indicates an error at the bus level.
+    ///
+    /// # Description
+    ///
+    /// After an overrun the FIFO content no longer forms a continuous sequence: in stream mode
+    /// the oldest entries have been overwritten, in FIFO mode acquisition has stopped. This
+    /// function reads the status, switches the FIFO to bypass mode, which empties it and clears
+    /// the flags, and then restores the previous `FifoMode`. The watermark and batching settings
+    /// are not modified. Entries overwritten before the call cannot be counted by the sensor and
+    /// are not included in `level`. Trigger modes (`StreamToFifoMode`, `BypassToStreamMode`,
+    /// `BypassToFifoMode`) are re-armed. Nothing is done when the FIFO is off, and a default
+    /// `FifoStatus` is returned.
+    pub async fn fifo_recover(&mut self) -> Result<FifoStatus, Error<B::Error>> {
+        let f_md = self.fifo_mode_get().await?;
+        if f_md.operation == FifoOperation::FifoOff {
+            return Ok(FifoStatus::default());
+        }
+
+        let status = self.fifo_status_get().await?;
+
+        self.fifo_mode_set(&FifoMode {
+            operation: FifoOperation::BypassMode,
+            ..f_md
+        })
+        .await?;
+        self.fifo_mode_set(&f_md).await?;
+
+        Ok(status)
+    }
+
+    /// Drains the FIFO into a `heapless` queue.
+    ///
+    /// # Arguments
+    ///
+    /// - `producer: &mut FifoProducer<N>`: The producer half of the queue with its decoder.
+    ///
+    /// # Returns
+    ///
+    /// - `Result<usize, Error<B::Error>>`:
+    ///   - `usize`: The number of FIFO entries read.
+    ///   - `Err`: Returns an error if the operation fails. Possible error variants include:
+    ///     - `Error::Bus`: Indicates an error at the bus level.
+    ///
+    /// # Description
+    ///
+    /// Intended to be called from the FIFO watermark interrupt handler. The FIFO status is read
+    /// once; an overrun is recorded in the producer statistics. All the entries present are
+    /// then read, decoded and enqueued. Entries are read even when the queue is full, so that
+    /// the sensor FIFO does not overrun as well; the dropped samples are counted in
+    /// `QueueStats::queue_full`. Available with the `heapless` feature.
+    #[cfg(feature = "heapless")]
+    pub async fn fifo_drain_to_queue<const N: usize>(
+        &mut self,
+        producer: &mut FifoProducer<'_, N>,
+    ) -> Result<usize, Error<B::Error>> {
+        let status = self.fifo_status_get().await?;
+        if status.ovr != 0 {
+            producer.note_fifo_overrun();
+        }
+
+        for _ in 0..status.level {
+            let record = self.fifo_record_get().await?;
+            producer.push(&record);
+        }
+
+        Ok(status.level as usize)
+    }
+
+    /// Applies a FIFO configuration computed by `FifoPlan::new`.
+    ///
+    /// # Arguments
+    ///
+    /// - `plan: &FifoPlan`: The planned FIFO configuration.
+    ///
+    /// # Returns
+    ///
+    /// - `Result<(), Error<B::Error>>`:
+    ///   - `Ok`: Indicates successful configuration of the FIFO.
+    ///   - `Err`: Returns an error if the operation fails. Possible error variants include:
+    ///     - `Error::Bus`: Indicates an error at the bus level.
+    ///
+    /// # Description
+    ///
+    /// This function writes the watermark, the batching and the FIFO mode of the plan, and
+    /// enables the timestamp counter when timestamps are batched. The FIFO watermark interrupt
+    /// must be routed separately.
+    pub async fn fifo_plan_apply(&mut self, plan: &FifoPlan) -> Result<(), Error<B::Error>> {
+        self.fifo_watermark_set(plan.watermark).await?;
+        self.fifo_batch_set(&plan.batch).await?;
+        if plan.batch.dec_ts != DecTs::Off {
+            self.timestamp_set(PROPERTY_ENABLE).await?;
+        }
+        self.fifo_mode_set(&plan.mode).await
+    }
+
+    /// Arms the FIFO to capture acceleration around an event.
+    ///
+    /// # Arguments
+    ///
+    /// - `trigger: CaptureTrigger`: The FIFO trigger mode.
+    /// - `event: CaptureEvent`: The event expected by `event_capture_wait`.
+    ///
+    /// # Returns
+    ///
+    /// - `Result<EventCapture, Error<B::Error>>`:
+    ///   - `EventCapture`: The capture state to pass to `event_capture_wait` and `event_capture_read`.
+    ///   - `Err`: Returns an error if the operation fails. Possible error variants include:
+    ///     - `Error::Bus`: Indicates an error at the bus level.
+    ///     - `Error::InvalidValue`: The ODR has no fixed rate.
+    ///
+    /// # Description
+    ///
+    /// The sensor mode, the event detection and its interrupt routing must be configured
+    /// beforehand. This function enables the timestamp counter, batches a timestamp with every
+    /// accelerometer sample (`DecTs::_1`), empties the FIFO through bypass mode and sets the
+    /// trigger mode, keeping the other `FifoMode` settings. Pending latched events are cleared
+    /// so that only a new event is reported. The previous timestamp decimation, timestamp
+    /// enable and FIFO operation mode are stored in the capture and restored by
+    /// `event_capture_disarm`.
+    pub async fn event_capture_arm(
+        &mut self,
+        trigger: CaptureTrigger,
+        event: CaptureEvent,
+    ) -> Result<EventCapture, Error<B::Error>> {
+        let md = self.mode_get().await?;
+        if md.odr.hz().is_none() {
+            return Err(Error::InvalidValue);
+        }
+
+        let prev_batch = self.fifo_batch_get().await?;
+        let prev_timestamp = self.timestamp_get().await?;
+        let prev_f_md = self.fifo_mode_get().await?;
+
+        let batch = Batch {
+            dec_ts: DecTs::_1,
+            ..prev_batch
+        };
+        self.fifo_batch_set(&batch).await?;
+        self.timestamp_set(PROPERTY_ENABLE).await?;
+
+        let f_md = FifoMode {
+            operation: FifoOperation::BypassMode,
+            ..prev_f_md
+        };
+        self.fifo_mode_set(&f_md).await?;
+        self.all_sources_get().await?;
+
+        let f_md = FifoMode {
+            operation: trigger.operation(),
+            ..f_md
+        };
+        self.fifo_mode_set(&f_md).await?;
+
+        let restore = CaptureRestore {
+            dec_ts: prev_batch.dec_ts,
+            timestamp: prev_timestamp,
+            operation: prev_f_md.operation,
+        };
+
+        Ok(
+            EventCapture::new(trigger, event, FifoDecoder::new(&md, &f_md, &batch))
+                .with_restore(restore),
+        )
+    }
+
+    /// Ends an event capture and restores the settings changed by `event_capture_arm`.
+    ///
+    /// # Arguments
+    ///
+    /// - `capture: &EventCapture`: The capture returned by `event_capture_arm`.
+    ///
+    /// # Returns
+    ///
+    /// - `Result<(), Error<B::Error>>`:
+    ///   - `Ok`: If the settings were restored successfully.
+    ///   - `Err`: Returns an error if the operation fails. Possible error variants include:
+    ///     - `Error::Bus`: Indicates an error at the bus level.
+    ///
+    /// # Description
+    ///
+    /// The FIFO operation mode, the timestamp batching decimation (`dec_ts`) and the timestamp
+    /// counter enable are set back to the values they had before `event_capture_arm`. Samples
+    /// still in the FIFO are discarded if the previous mode is bypass or off. Nothing is done
+    /// for a capture created with `EventCapture::new`.
+    pub async fn event_capture_disarm(
+        &mut self,
+        capture: &EventCapture,
+    ) -> Result<(), Error<B::Error>> {
+        let Some(restore) = capture.restore() else {
+            return Ok(());
+        };
+
+        let f_md = FifoMode {
+            operation: restore.operation,
+            ..self.fifo_mode_get().await?
+        };
+        self.fifo_mode_set(&f_md).await?;
+
+        let batch = Batch {
+            dec_ts: restore.dec_ts,
+            ..self.fifo_batch_get().await?
+        };
+        self.fifo_batch_set(&batch).await?;
+        self.timestamp_set(restore.timestamp).await
+    }
+
+    /// Waits for the event of an armed capture.
+    ///
+    /// # Arguments
+    ///
+    /// - `capture: &mut EventCapture`: The capture returned by `event_capture_arm`.
+    /// - `timeout_ms: u32`: Maximum waiting time in milliseconds.
+    ///
+    /// # Returns
+    ///
+    /// - `Result<bool, Error<B::Error>>`:
+    ///   - `bool`: `true` if the event occurred, `false` on timeout.
+    ///   - `Err`: Returns an error if the operation fails. Possible error variants include:
+    ///     - `Error::Bus`: Indicates an error at the bus level.
+    ///
+    /// # Description
+    ///
+    /// The event sources are polled with `all_sources_get` once right away and then after each
+    /// of the `timeout_ms` 1 ms delays, so a zero timeout polls once. When the event is flagged,
+    /// the timestamp register is read and stored as the event time. Applications waiting on the
+    /// interrupt pin can instead call `EventCapture::set_event_time` with `timestamp_raw_get`
+    /// from the interrupt handler.
+    pub async fn event_capture_wait(
+        &mut self,
+        capture: &mut EventCapture,
+        timeout_ms: u32,
+    ) -> Result<bool, Error<B::Error>> {
+        let mut elapsed_ms = 0;
+        loop {
+            let sources = self.all_sources_get().await?;
+            if capture.event().is_set(&sources) {
+                capture.set_event_time(self.timestamp_raw_get().await?);
+                return Ok(true);
+            }
+            if elapsed_ms >= timeout_ms {
+                return Ok(false);
+            }
+            self.tim.delay_ms(1).await;
+            elapsed_ms += 1;
+        }
+    }
+
+    /// Reads the samples of a capture from the FIFO.
+    ///
+    /// # Arguments
+    ///
+    /// - `capture: &mut EventCapture`: The triggered capture.
+    /// - `out: &mut [CapturedSample]`: Destination of the accelerometer samples, oldest first.
+    ///
+    /// # Returns
+    ///
+    /// - `Result<usize, Error<B::Error>>`:
+    ///   - `usize`: The number of samples written to `out`.
+    ///   - `Err`: Returns an error if the operation fails. Possible error variants include:
+    ///     - `Error::Bus`: Indicates an error at the bus level.
+    ///
+    /// # Description
+    ///
+    /// The FIFO entries present when the function is called are read until `out` is full. The
+    /// second sample of an `AccelPair` record that does not fit in `out` is kept in the capture
+    /// and written first by the next call. Each sample carries its offset from the event:
+    /// negative offsets are pre-trigger samples. With `CaptureTrigger::StreamToFifo`, wait for
+    /// `FifoStatus::full` to get the complete post-trigger window. With
+    /// `CaptureTrigger::BypassToStream` the function can be called repeatedly while the FIFO
+    /// keeps streaming. Call `event_capture_arm` again for the next event, or
+    /// `event_capture_disarm` to end the capture.
+    pub async fn event_capture_read(
+        &mut self,
+        capture: &mut EventCapture,
+        out: &mut [CapturedSample],
+    ) -> Result<usize, Error<B::Error>> {
+        let mut count = 0;
+        if !out.is_empty() {
+            if let Some(sample) = capture.take_pending() {
+                out[0] = sample;
+                count = 1;
+            }
+        }
+
+        let level = self.fifo_data_level_get().await?;
+        for _ in 0..level {
+            if count == out.len() {
+                break;
+            }
+            let record = self.fifo_record_get().await?;
+            count += capture.push(&record, &mut out[count..]);
+        }
+
+        Ok(count)
+    }
+
     /// Retrieves the sensor tag from the FIFO data output.
     ///
     /// # Returns
@@ -2120,6 +2806,70 @@ impl<B: BusOperation, T: DelayNs> Iis2dulpx<B, T, MainBank> {
         Ok(buff)
     }
 
+    /// Retrieves one raw tagged record from the FIFO.
+    ///
+    /// # Returns
+    ///
+    /// - `Result<FifoRecord, Error<B::Error>>`:
+    ///   - `FifoRecord`: The sensor tag and the 6 data bytes of the oldest FIFO entry.
+    ///   - `Err`: Returns an error if the operation fails. Possible error variants include:
+    ///     - `Error::Bus`: Indicates an error at the bus level.
+    ///
+    /// # Description
+    ///
+    /// The `FifoDataOutTag` register and the six data registers that follow it are read in a single
+    /// 7-byte burst. Register address auto-increment (`IF_ADD_INC`, set by `init_set`) must be enabled.
+    pub async fn fifo_record_get(&mut self) -> Result<FifoRecord, Error<B::Error>> {
+        let mut buff = [0; FIFO_RECORD_LEN];
+        self.read_from_register(Reg::FifoDataOutTag as u8, &mut buff)
+            .await?;
+
+        Ok(FifoRecord::from_bytes(&buff))
+    }
+
+    /// Drains the FIFO into a caller-provided buffer of raw records.
+    ///
+    /// # Arguments
+    ///
+    /// - `buf: &mut [FifoRecord]`: Destination of the records, oldest first.
+    ///
+    /// # Returns
+    ///
+    /// - `Result<usize, Error<B::Error>>`:
+    ///   - `usize`: The number of records written to `buf`.
+    ///   - `Err`: Returns an error if the operation fails. Possible error variants include:
+    ///     - `Error::Bus`: Indicates an error at the bus level.
+    ///
+    /// # Description
+    ///
+    /// The FIFO level is read once with `fifo_data_level_get`, then at most `buf.len()` entries are
+    /// read in bursts of up to `FIFO_READ_CHUNK` records. Once the last data register
+    /// (`FIFO_DATA_OUT_Z_H`) has been read, the address auto-increment rolls back to
+    /// `FIFO_DATA_OUT_TAG` and the next entry is output, so each burst is a sequence of 7-byte
+    /// records that are parsed with `FifoRecord::from_bytes`. Register address auto-increment
+    /// (`IF_ADD_INC`, set by `init_set`) must be enabled. Entries that do not fit in `buf` are left
+    /// in the FIFO. The records can be decoded afterwards, independently of the bus.
+    pub async fn fifo_read_batch(
+        &mut self,
+        buf: &mut [FifoRecord],
+    ) -> Result<usize, Error<B::Error>> {
+        let level = self.fifo_data_level_get().await? as usize;
+        let count = level.min(buf.len());
+        let mut chunk = [0u8; FIFO_RECORD_LEN * FIFO_READ_CHUNK];
+
+        for records in buf[..count].chunks_mut(FIFO_READ_CHUNK) {
+            let bytes = &mut chunk[..records.len() * FIFO_RECORD_LEN];
+            self.read_from_register(Reg::FifoDataOutTag as u8, bytes)
+                .await?;
+
+            for (record, raw) in records.iter_mut().zip(bytes.chunks_exact(FIFO_RECORD_LEN)) {
+                *record = FifoRecord::from_bytes(raw.try_into().unwrap());
+            }
+        }
+
+        Ok(count)
+    }
+
     /// Retrieves and processes FIFO data based on the sensor and FIFO mode configurations.
     ///
     /// # Arguments
@@ -2129,107 +2879,50 @@ impl<B: BusOperation, T: DelayNs> Iis2dulpx<B, T, MainBank> {
     ///
     /// # Returns
     ///
-    /// - `Result<FifoData, Error<B::Error>>`:
-    ///   - `FifoData`: The processed FIFO data.
+    /// - `Result<FifoSample, Error<B::Error>>`:
+    ///   - `FifoSample`: The decoded FIFO record.
     ///   - `Err`: Returns an error if the operation fails. Possible error variants include:
     ///     - `Error::Bus`: Indicates an error at the bus level.
     ///
     /// # Description
     ///
     /// This function retrieves and processes FIFO data based on the provided sensor and FIFO mode configurations.
-    /// It reads the `FifoDataOutTag` and `FifoDataOutXL` registers to obtain raw data and processes it according
+    /// It reads one record with `fifo_record_get` and decodes it with `FifoRecord::decode` according
     /// to the sensor tag and mode settings.
     pub async fn fifo_data_get(
         &mut self,
         md: &Md,
         f_md: &FifoMode,
-    ) -> Result<FifoData, Error<B::Error>> {
-        let fifo_tag = FifoDataOutTag::read(self).await?;
-
-        let tag_sensor = FifoSensorTag::try_from(fifo_tag.tag_sensor()).unwrap_or_default();
-
-        let fifo_raw: [u8; 6] = self.fifo_out_raw_get().await?;
-
-        let mut data = FifoData {
-            tag: fifo_tag.tag_sensor(),
-            ..Default::default()
-        };
-
-        match tag_sensor {
-            FifoSensorTag::XlOnly2xTag | FifoSensorTag::XlOnly2xTag2nd => {
-                // A FIFO sample consists of 2X 8-bits 3-axis XL at ODR/2
-                for i in 0..3 {
-                    data.xl[0].raw[i] = i16::from_le_bytes([0, fifo_raw[i]]);
-                    data.xl[1].raw[i] = i16::from_le_bytes([0, fifo_raw[3 + i]]);
-                }
-            }
-            FifoSensorTag::XlAndQvar | FifoSensorTag::XlTempTag => {
-                if f_md.xl_only == 0 {
-                    // A FIFO sample consists of 12-bits 3-axis XL + T at ODR
-                    data.xl[0].raw[0] = ((fifo_raw[0] as i16) + ((fifo_raw[1] as i16) << 8)) << 4;
-                    data.xl[0].raw[1] =
-                        (((fifo_raw[1] as i16) >> 4) + ((fifo_raw[2] as i16) << 4)) << 4;
-                    data.xl[0].raw[2] = ((fifo_raw[3] as i16) + ((fifo_raw[4] as i16) << 8)) << 4;
-
-                    data.heat.raw = (fifo_raw[4] as i16) >> 4;
-
-                    if tag_sensor == FifoSensorTag::XlTempTag {
-                        data.heat.deg_c = from_lsb_to_celsius(data.heat.raw);
-                    } else {
-                        data.ah_qvar.raw = data.heat.raw;
-                        data.ah_qvar.mv = from_lsb_to_mv(data.ah_qvar.raw);
-                    }
-                } else {
-                    // A FIFO sample consists of 16-bits 3-axis XL at ODR
-                    data.xl[0].raw[0] = i16::from_le_bytes([fifo_raw[0], fifo_raw[1]]);
-                    data.xl[0].raw[1] = i16::from_le_bytes([fifo_raw[2], fifo_raw[3]]);
-                    data.xl[0].raw[2] = i16::from_le_bytes([fifo_raw[4], fifo_raw[5]]);
-                }
-            }
-            FifoSensorTag::TimestampTag => {
-                data.cfg_chg.cfg_change = fifo_raw[0] >> 7;
-                data.cfg_chg.odr = (fifo_raw[0] >> 3) & 0xF;
-                data.cfg_chg.bw = (fifo_raw[0] >> 1) & 0x3;
-                data.cfg_chg.lp_hp = fifo_raw[0] & 0x1;
-                data.cfg_chg.qvar_en = fifo_raw[1] >> 7;
-                data.cfg_chg.fs = (fifo_raw[1] >> 5) & 0x3;
-                data.cfg_chg.dec_ts = (fifo_raw[1] >> 3) & 0x3;
-                data.cfg_chg.odr_xl_batch = fifo_raw[1] & 0x7;
-
-                data.cfg_chg.timestamp =
-                    u32::from_le_bytes([fifo_raw[2], fifo_raw[3], fifo_raw[4], fifo_raw[5]]);
-            }
-            FifoSensorTag::StepCounterTag => {
-                data.pedo.steps = u32::from_le_bytes([fifo_raw[0], fifo_raw[1], 0, 0]);
+    ) -> Result<FifoSample, Error<B::Error>> {
+        let record = self.fifo_record_get().await?;
 
-                data.pedo.timestamp =
-                    u32::from_le_bytes([fifo_raw[2], fifo_raw[3], fifo_raw[4], fifo_raw[5]]);
-            }
-            _ => {}
-        }
+        Ok(record.to_sample(&FifoContext::new(md, f_md)))
+    }
 
-        for i in 0..3 {
-            match md.fs {
-                Fs::_2g => {
-                    data.xl[0].mg[i] = from_fs2g_to_mg(data.xl[0].raw[i]);
-                    data.xl[1].mg[i] = from_fs2g_to_mg(data.xl[1].raw[i]);
-                }
-                Fs::_4g => {
-                    data.xl[0].mg[i] = from_fs4g_to_mg(data.xl[0].raw[i]);
-                    data.xl[1].mg[i] = from_fs4g_to_mg(data.xl[1].raw[i]);
-                }
-                Fs::_8g => {
-                    data.xl[0].mg[i] = from_fs8g_to_mg(data.xl[0].raw[i]);
-                    data.xl[1].mg[i] = from_fs8g_to_mg(data.xl[1].raw[i]);
-                }
-                Fs::_16g => {
-                    data.xl[0].mg[i] = from_fs16g_to_mg(data.xl[0].raw[i]);
-                    data.xl[1].mg[i] = from_fs16g_to_mg(data.xl[1].raw[i]);
-                }
-            }
-        }
+    /// Retrieves and decodes one FIFO record with a stateful decoder.
+    ///
+    /// # Arguments
+    ///
+    /// - `decoder: &mut FifoDecoder`: The decoder tracking the sensor configuration.
+    ///
+    /// # Returns
+    ///
+    /// - `Result<FifoSample, Error<B::Error>>`:
+    ///   - `FifoSample`: The decoded FIFO record.
+    ///   - `Err`: Returns an error if the operation fails. Possible error variants include:
+    ///     - `Error::Bus`: Indicates an error at the bus level.
+    ///
+    /// # Description
+    ///
+    /// Unlike `fifo_data_get`, the configuration is not passed by the caller but taken from the
+    /// decoder, which updates it on configuration-change records (`cfg_change_in_fifo` enabled).
+    pub async fn fifo_decoded_get(
+        &mut self,
+        decoder: &mut FifoDecoder,
+    ) -> Result<FifoSample, Error<B::Error>> {
+        let record = self.fifo_record_get().await?;
 
-        Ok(data)
+        Ok(decoder.decode(&record))
     }
 
     /// Enables and configures the AH_QVAR chain.
@@ -2292,6 +2985,99 @@ impl<B: BusOperation, T: DelayNs> Iis2dulpx<B, T, MainBank> {
         Ok(val)
     }
 
+    /// Configures the combined accelerometer and AH_QVAR acquisition.
+    ///
+    /// # Arguments
+    ///
+    /// - `val: &QvarAcquisition`: The acquisition settings.
+    ///
+    /// # Returns
+    ///
+    /// - `Result<(), Error<B::Error>>`:
+    ///   - `Ok`: Indicates successful configuration.
+    ///   - `Err`: Returns an error if the operation fails. Possible error variants include:
+    ///     - `Error::Bus`: Indicates an error at the bus level.
+    ///     - `Error::InvalidOdrForQvar`: The ODR is off, ultra-low-power or triggered.
+    ///     - `Error::InvalidBwForODR`: Indicates an invalid bandwidth value for the selected ODR.
+    ///
+    /// # Description
+    ///
+    /// AH_QVAR is sampled together with the accelerometer through the temperature chain, so the
+    /// chain is enabled (`t_ah_qvar_dis = 0`) and the temperature is no longer available. The
+    /// function applies the accelerometer mode, enables and configures the AH_QVAR front-end and,
+    /// unless `fifo_operation` is `FifoOff`, sets the FIFO in 1x depth with the 12-bit format
+    /// (`xl_only = 0`) so that each FIFO word is an `XlAndQvar` record. With `FifoOff` the FIFO
+    /// is turned off and its depth and format are left unchanged.
+    pub async fn qvar_acquisition_set(
+        &mut self,
+        val: &QvarAcquisition,
+    ) -> Result<(), Error<B::Error>> {
+        match val.md.odr {
+            Odr::Off
+            | Odr::_1_6hzUlp
+            | Odr::_3hzUlp
+            | Odr::_25hzUlp
+            | Odr::TrigPin
+            | Odr::TrigSw => return Err(Error::InvalidOdrForQvar),
+            _ => {}
+        }
+
+        self.mode_set(&val.md).await?;
+        self.t_ah_qvar_dis_set(PROPERTY_DISABLE).await?;
+
+        let qvar_mode = AhQvarMode {
+            ah_qvar_en: PROPERTY_ENABLE,
+            ah_qvar_notch_en: val.ah_qvar_notch_en,
+            ah_qvar_notch: val.ah_qvar_notch,
+            ah_qvar_zin: val.ah_qvar_zin,
+            ah_qvar_gain: val.ah_qvar_gain,
+        };
+        self.ah_qvar_mode_set(&qvar_mode).await?;
+
+        let mut fifo_mode = FifoMode {
+            operation: val.fifo_operation,
+            ..self.fifo_mode_get().await?
+        };
+        if val.fifo_operation != FifoOperation::FifoOff {
+            fifo_mode.store = Store::Fifo1x;
+            fifo_mode.xl_only = PROPERTY_DISABLE;
+        }
+        self.fifo_mode_set(&fifo_mode).await
+    }
+
+    /// Retrieves a paired accelerometer and AH_QVAR sample from the output registers.
+    ///
+    /// # Arguments
+    ///
+    /// - `md: &Md`: The sensor mode, used to convert the acceleration to milli-g.
+    ///
+    /// # Returns
+    ///
+    /// - `Result<XlQvarData, Error<B::Error>>`:
+    ///   - `XlQvarData`: The acceleration and the AH_QVAR value.
+    ///   - `Err`: Returns an error if the operation fails. Possible error variants include:
+    ///     - `Error::Bus`: Indicates an error at the bus level.
+    ///
+    /// # Description
+    ///
+    /// The AH_QVAR value is read first, then the acceleration; reading `OUT_Z_H` last clears the
+    /// data-ready flag for both.
+    pub async fn xl_qvar_data_get(&mut self, md: &Md) -> Result<XlQvarData, Error<B::Error>> {
+        let ah_qvar = OutTAhQvar::read(self).await?.outt().cast_signed();
+        let xl = self.xl_data_get(md).await?;
+
+        Ok(XlQvarData {
+            xl: Xl {
+                mg: xl.mg,
+                raw: xl.raw,
+            },
+            ah_qvar: AhQvar {
+                mv: from_lsb_to_mv(ah_qvar),
+                raw: ah_qvar,
+            },
+        })
+    }
+
     /// Sets the step counter mode.
     ///
     /// # Arguments
@@ -2996,6 +3782,23 @@ impl<B: BusOperation, T: DelayNs> Iis2dulpx<B, T, MainBank> {
         Ok(Timestamp::read(self).await?.timestamp())
     }
 
+    /// Retrieves the current timestamp as a `SensorTime`.
+    ///
+    /// # Returns
+    ///
+    /// - `Result<SensorTime, Error<B::Error>>`:
+    ///   - `SensorTime`: The current value of the timestamp counter.
+    ///   - `Err`: Returns an error if the operation fails.
+    ///
+    /// # Description
+    ///
+    /// Same as `timestamp_raw_get`, with the value wrapped in a `SensorTime` for conversion to
+    /// microseconds or `Duration`. The value is not extended across counter rollovers; feed it
+    /// to a `TimestampExtender` for a monotonic timeline.
+    pub async fn timestamp_time_get(&mut self) -> Result<SensorTime, Error<B::Error>> {
+        Ok(SensorTime::from_raw(self.timestamp_raw_get().await?))
+    }
+
     /// Retrieves the FSM long counter timeout interrupt status.
     ///
     /// # Returns
@@ -3182,6 +3985,48 @@ impl<B: BusOperation, T: DelayNs> Iis2dulpx<B, T, MainBank> {
             .await
     }
 
+    /// Retrieves the decoded outputs of the FSM programs.
+    ///
+    /// # Returns
+    ///
+    /// - `Result<[FsmOutput; 8], Error<B::Error>>`:
+    ///   - `[FsmOutput; 8]`: The outputs of FSM1 to FSM8, from the `FSM_OUTS1` to `FSM_OUTS8` registers.
+    ///   - `Err`: Returns an error if the operation fails. Possible error variants include:
+    ///     - `Error::Bus`: Indicates an error at the bus level.
+    ///
+    /// # Description
+    ///
+    /// This function reads the FSM output registers and decodes the direction flags of each program.
+    pub async fn fsm_outputs_get(&mut self) -> Result<[FsmOutput; 8], Error<B::Error>> {
+        let outs = self.fsm_out_get().await?;
+        Ok(outs.map(FsmOutput::from))
+    }
+
+    /// Retrieves the FSM programs that fired, with their output.
+    ///
+    /// # Returns
+    ///
+    /// - `Result<FsmEvents, Error<B::Error>>`:
+    ///   - `FsmEvents`: Iterator over the programs flagged in `FSM_STATUS_MAINPAGE`, yielding their
+    ///     index (0 for FSM1) and decoded output.
+    ///   - `Err`: Returns an error if the operation fails. Possible error variants include:
+    ///     - `Error::Bus`: Indicates an error at the bus level.
+    ///
+    /// # Description
+    ///
+    /// This function reads the `FSM_STATUS_MAINPAGE` register and, when at least one program fired,
+    /// the FSM output registers.
+    pub async fn fsm_events_get(&mut self) -> Result<FsmEvents, Error<B::Error>> {
+        let status = self.fsm_status_get().await?;
+        let outs = if status.into_bits() != 0 {
+            self.fsm_out_get().await?
+        } else {
+            [0; 8]
+        };
+
+        Ok(FsmEvents::new(status, outs))
+    }
+
     /// Configures the FSM output data rate (ODR).
     ///
     /// # Arguments
@@ -3419,6 +4264,47 @@ impl<B: BusOperation, T: DelayNs> Iis2dulpx<B, T, MainBank> {
         Ok(FsmStartAdd::read(self).await?.fsm_start())
     }
 
+    /// Loads FSM programs and enables them.
+    ///
+    /// # Arguments
+    ///
+    /// - `programs: &[FsmProgram]`: The programs, FSM1 first.
+    ///
+    /// # Returns
+    ///
+    /// - `Result<FsmLayout, Error<B::Error>>`:
+    ///   - `FsmLayout`: The placement of the programs, with a handle for each one.
+    ///   - `Err`: Returns an error if the operation fails. Possible error variants include:
+    ///     - `Error::Bus`: Indicates an error at the bus level.
+    ///     - `Error::FsmLayout`: The programs do not fit in the FSM memory. Nothing has been
+    ///       written.
+    ///
+    /// # Description
+    ///
+    /// Disables all the FSMs, sets `FSM_PROGRAMS` and `FSM_START_ADD` (0x19C), writes the
+    /// programs one after the other from the start address, requests an FSM initialization
+    /// and finally enables the programs built as enabled. The FSM data rate, the interrupt
+    /// routing and the embedded functions enable in `CTRL4` are left to the caller.
+    pub async fn fsm_load_programs(
+        &mut self,
+        programs: &[FsmProgram<'_>],
+    ) -> Result<FsmLayout, Error<B::Error>> {
+        let layout = FsmLayout::new(programs).map_err(Error::FsmLayout)?;
+
+        self.fsm_enable_set(&FsmEnable::new()).await?;
+        self.fsm_programs_num_set(layout.count()).await?;
+        self.fsm_start_address_set(FSM_START_ADDRESS).await?;
+        for (program, handle) in programs.iter().zip(layout.handles()) {
+            self.ln_pg_write(handle.address, program.bytes(), handle.len)
+                .await?;
+        }
+
+        self.fsm_init_set(PROPERTY_ENABLE).await?;
+        self.fsm_enable_set(&layout.enable()).await?;
+
+        Ok(layout)
+    }
+
     /// Configures the Machine Learning Core (MLC) mode.
     ///
     /// # Arguments
@@ -3631,6 +4517,78 @@ impl<B: BusOperation, T: DelayNs> Iis2dulpx<B, T, MainBank> {
             .await
             .map(|reg| reg.mlc_fifo_en())
     }
+
+    /// Configures which embedded function results are batched in the FIFO.
+    ///
+    /// # Arguments
+    ///
+    /// - `val: &EmbFifoBatching`: The batching settings for the embedded functions.
+    ///
+    /// # Returns
+    ///
+    /// - `Result<(), Error<B::Error>>`:
+    ///   - `Ok`: Indicates successful configuration of the FIFO batching.
+    ///   - `Err`: Returns an error if the operation fails. Possible error variants include:
+    ///     - `Error::Bus`: Indicates an error at the bus level.
+    ///     - `Error::EmbFuncNotEnabled`: Batching is requested for a function that is disabled.
+    ///
+    /// # Description
+    ///
+    /// This function writes all the fields of the `EMB_FUNC_FIFO_EN` register at once. Each
+    /// batching bit can only be set if the corresponding function is enabled: the pedometer
+    /// (`pedo_en`) for the step counter, the MLC (`mlc_en` or `mlc_before_fsm_en`) for its
+    /// results, filters and features, and the FSM (`fsm_en`) for its results. Enable the
+    /// functions first, for example with `stpcnt_mode_set`, `mlc_set` and `fsm_enable_set`.
+    /// The register is left unchanged when the validation fails.
+    pub async fn emb_fifo_batching_set(
+        &mut self,
+        val: &EmbFifoBatching,
+    ) -> Result<(), Error<B::Error>> {
+        self.operate_over_emb(async |state| {
+            let emb_en_a = EmbFuncEnA::read(state).await?;
+            let emb_en_b = EmbFuncEnB::read(state).await?;
+            let mlc_en = emb_en_a.mlc_before_fsm_en() | emb_en_b.mlc_en();
+
+            if (val.step_counter == PROPERTY_ENABLE && emb_en_a.pedo_en() == PROPERTY_DISABLE)
+                || (val.mlc == PROPERTY_ENABLE && mlc_en == PROPERTY_DISABLE)
+                || (val.mlc_filter_feature == PROPERTY_ENABLE && mlc_en == PROPERTY_DISABLE)
+                || (val.fsm == PROPERTY_ENABLE && emb_en_b.fsm_en() == PROPERTY_DISABLE)
+            {
+                return Err(Error::EmbFuncNotEnabled);
+            }
+
+            let mut fifo_reg = EmbFuncFifoEn::read(state).await?;
+            fifo_reg.set_step_counter_fifo_en(val.step_counter);
+            fifo_reg.set_mlc_fifo_en(val.mlc);
+            fifo_reg.set_mlc_filter_feature_fifo_en(val.mlc_filter_feature);
+            fifo_reg.set_fsm_fifo_en(val.fsm);
+            fifo_reg.write(state).await
+        })
+        .await
+    }
+
+    /// Retrieves which embedded function results are batched in the FIFO.
+    ///
+    /// # Returns
+    ///
+    /// - `Result<EmbFifoBatching, Error<B::Error>>`:
+    ///   - `EmbFifoBatching`: The batching settings read from the `EMB_FUNC_FIFO_EN` register.
+    ///   - `Err`: Returns an error if the operation fails. Possible error variants include:
+    ///     - `Error::Bus`: Indicates an error at the bus level.
+    ///
+    /// # Description
+    ///
+    /// This function reads the `EMB_FUNC_FIFO_EN` register and returns all its batching bits.
+    pub async fn emb_fifo_batching_get(&mut self) -> Result<EmbFifoBatching, Error<B::Error>> {
+        let reg = self.operate_over_emb(EmbFuncFifoEn::read).await?;
+
+        Ok(EmbFifoBatching {
+            step_counter: reg.step_counter_fifo_en(),
+            mlc: reg.mlc_fifo_en(),
+            mlc_filter_feature: reg.mlc_filter_feature_fifo_en(),
+            fsm: reg.fsm_fifo_en(),
+        })
+    }
 }
 
 #[bisync]
@@ -3796,6 +4754,25 @@ pub fn from_lsb_to_celsius(lsb: i16) -> f32 {
     (lsb as f32 / 355.5) + 25.0
 }
 
+/// Converts raw FIFO temperature data from LSB to degrees Celsius.
+///
+/// # Arguments
+///
+/// - `lsb: i16`: The sign-extended 12-bit temperature data packed in FIFO words.
+///
+/// # Returns
+///
+/// - `f32`: The converted temperature value in degrees Celsius.
+///
+/// # Description
+///
+/// In the 12-bit FIFO format the temperature keeps only the 12 most significant bits of the
+/// `OUT_T_AH_QVAR` value, so the sensitivity is 16 times lower than the register one. The
+/// conversion formula used is `(lsb * 16 / 355.5) + 25.0`.
+pub fn from_fifo_lsb_to_celsius(lsb: i16) -> f32 {
+    (lsb as f32 * 16.0 / 355.5) + 25.0
+}
+
 /// Converts raw AH_QVAR data from LSB to millivolts.
 ///
 /// # Arguments
@@ -3813,6 +4790,103 @@ pub fn from_lsb_to_mv(lsb: i16) -> f32 {
     (lsb as f32) / 74.4
 }
 
+/// Converts raw FIFO AH_QVAR data from LSB to millivolts.
+///
+/// # Arguments
+///
+/// - `lsb: i16`: The sign-extended 12-bit AH_QVAR data packed in FIFO words.
+///
+/// # Returns
+///
+/// - `f32`: The converted voltage value in millivolts.
+///
+/// # Description
+///
+/// Same as `from_lsb_to_mv` for the 12-bit FIFO format, where the value is 16 times smaller
+/// than the `OUT_T_AH_QVAR` register one.
+pub fn from_fifo_lsb_to_mv(lsb: i16) -> f32 {
+    (lsb as f32 * 16.0) / 74.4
+}
+
+/// Converts an IEEE 754 half-precision value to `f32`.
+///
+/// # Arguments
+///
+/// - `half: u16`: The half-precision bit pattern.
+///
+/// # Returns
+///
+/// - `f32`: The equivalent single-precision value.
+///
+/// # Description
+///
+/// The Machine Learning Core stores filter and feature values in half-precision format, both
+/// in its registers and in the FIFO. Subnormals, infinities and NaN are converted exactly.
+pub fn from_f16_to_f32(half: u16) -> f32 {
+    let sign = ((half as u32) & 0x8000) << 16;
+    let exp = ((half >> 10) & 0x1F) as u32;
+    let mant = (half & 0x3FF) as u32;
+
+    match exp {
+        0 => {
+            // zero or subnormal: mant * 2^-24
+            let value = mant as f32 / 16_777_216.0;
+            f32::from_bits(sign | value.to_bits())
+        }
+        0x1F => f32::from_bits(sign | 0x7F80_0000 | (mant << 13)),
+        _ => f32::from_bits(sign | ((exp + 112) << 23) | (mant << 13)),
+    }
+}
+
+/// Converts a single-precision float into a half-precision bit pattern.
+///
+/// # Arguments
+///
+/// - `value: f32`: The value to convert.
+///
+/// # Returns
+///
+/// - `u16`: The nearest half-precision value, ties to even.
+///
+/// # Description
+///
+/// Used for the thresholds of FSM programs. Values too large for half precision become
+/// infinities, values too small become zeros or subnormals, NaN stays NaN.
+pub fn from_f32_to_f16(value: f32) -> u16 {
+    let bits = value.to_bits();
+    let sign = ((bits >> 16) & 0x8000) as u16;
+    let exp = ((bits >> 23) & 0xFF) as i32;
+    let mant = bits & 0x7F_FFFF;
+
+    if exp == 0xFF {
+        return sign | 0x7C00 | if mant != 0 { 0x200 } else { 0 };
+    }
+
+    let exp = exp - 127 + 15;
+    if exp >= 0x1F {
+        return sign | 0x7C00;
+    }
+
+    // drop the low mantissa bits, rounding to nearest even; a carry into the exponent gives
+    // the next power of two, or infinity
+    let round = |half: u32, rem: u32, halfway: u32| {
+        half + (rem > halfway || (rem == halfway && half & 1 != 0)) as u32
+    };
+
+    if exp <= 0 {
+        if exp < -10 {
+            return sign;
+        }
+        // subnormal: (1.mant) * 2^(exp - 15) = m * 2^-24
+        let mant = mant | 0x80_0000;
+        let shift = (14 - exp) as u32;
+        let half = round(mant >> shift, mant & ((1 << shift) - 1), 1 << (shift - 1));
+        return sign | half as u16;
+    }
+
+    sign | round(((exp as u32) << 10) | (mant >> 13), mant & 0x1FFF, 0x1000) as u16
+}
+
 /// Represents the I2C addresses for the sensor.
 ///
 /// This enum is used to specify the possible I2C addresses that the sensor can use for communication.
@@ -3838,5 +4912,10 @@ const SW_RESET_DELAY_US: u8 = 50;
 
 const BOOT_SWRESET_MAX_ATTEMPTS: u8 = 5;
 
+/// Size of a FIFO entry: `FIFO_DATA_OUT_TAG` and the six data bytes.
+const FIFO_RECORD_LEN: usize = 7;
+/// Maximum number of FIFO entries read in a single burst by `fifo_read_batch`.
+pub const FIFO_READ_CHUNK: usize = 16;
+
 pub const PROPERTY_ENABLE: u8 = 1;
 pub const PROPERTY_DISABLE: u8 = 0;
@@ -0,0 +1,1138 @@
+//! FIFO record decoding.
+//!
+//! Decoding of the raw FIFO entries is independent of the bus: a [`FifoRecord`] read with
+//! `fifo_record_get` or `fifo_read_batch`, or recorded elsewhere, is turned into a
+//! [`FifoSample`] with [`FifoRecord::decode`] given the [`FifoContext`] it was acquired in.
+//!
+//! [`FifoDecoder`] adds the state needed to decode a stream of records: it follows the
+//! configuration changes reported in the FIFO and assigns a timestamp to every accelerometer
+//! sample.
+//!
+//! [`FifoPlan`] derives the FIFO configuration from the acceptable latency or host wake-up rate.
+
+use super::driver::{
+    from_f16_to_f32, from_fifo_lsb_to_celsius, from_fifo_lsb_to_mv, from_fs2g_to_mg,
+    from_fs4g_to_mg, from_fs8g_to_mg, from_fs16g_to_mg,
+};
+use super::register::main::{
+    AhQvar, Batch, BdrXl, Bw, CfgChg, DecTs, FifoMode, FifoOperation, FifoRecord, FifoSensorTag,
+    Fs, FsmFifoResult, Heat, Md, MlcFifoResult, MlcFifoValue, Odr, Pedo, Store, Xl, XlQvarData,
+};
+use super::timestamp::{SensorTime, TIMESTAMP_TICK_US, TimestampExtender};
+
+/// Number of entries (tag and six data bytes) the FIFO can hold.
+pub const FIFO_DEPTH: u8 = 128;
+
+/// Represents the sensor configuration needed to decode FIFO records.
+///
+/// # Fields
+///
+/// - `fs: Fs`: Full-scale range, used to convert acceleration to milli-g.
+/// - `xl_only: u8`: Value of the `xl_only` FIFO setting. When 0, accelerometer records use the
+///   12-bit format that packs temperature or AH_QVAR with the acceleration.
+///
+/// # Description
+///
+/// This struct collects the settings that determine the meaning of the FIFO record bytes.
+#[derive(Clone, Copy, PartialEq, Default)]
+pub struct FifoContext {
+    pub fs: Fs,
+    pub xl_only: u8,
+}
+
+impl FifoContext {
+    /// Builds the context from the sensor and FIFO mode configurations.
+    pub fn new(md: &Md, f_md: &FifoMode) -> Self {
+        Self {
+            fs: md.fs,
+            xl_only: f_md.xl_only,
+        }
+    }
+}
+
+/// Represents a decoded FIFO record.
+///
+/// # Variants
+///
+/// - `Empty`: The FIFO was empty.
+/// - `Accel`: 16-bit accelerometer sample (`xl_only = 1`).
+/// - `AccelTemp`: 12-bit accelerometer sample with the temperature (`XlTempTag`, `xl_only = 0`).
+/// - `AccelQvar`: 12-bit accelerometer sample with the AH_QVAR value (`XlAndQvar`, `xl_only = 0`).
+/// - `AccelPair`: Two consecutive 8-bit accelerometer samples of the 2x depth
+///   (`Store::Fifo2x`, tags `XlOnly2xTag` and `XlOnly2xTag2nd`), oldest first.
+/// - `Timestamp`: Timestamp record, in 10 µs ticks.
+/// - `ConfigChange`: Timestamp record flagging a configuration change.
+/// - `Steps`: Step counter record.
+/// - `MlcResult`: Machine Learning Core decision tree result.
+/// - `MlcFilter`: Machine Learning Core filter value.
+/// - `MlcFeature`: Machine Learning Core feature value.
+/// - `FsmResult`: Finite State Machine program result.
+/// - `Unknown`: Record with an invalid tag, with its raw content.
+///
+/// # Description
+///
+/// Each variant only carries the data that is valid for the corresponding tag, so callers can
+/// match on the variant instead of checking the raw tag.
+///
+/// Accelerometer `raw` values are left-aligned to 16 bits whatever the record format and `mg`
+/// is converted from them with the 16-bit sensitivity of the full scale. The effective
+/// resolution, see [`FifoSample::resolution_bits`], is 16 bits for `Accel`, 12 bits for
+/// `AccelTemp` and `AccelQvar` (the 4 LSBs are zero) and 8 bits for `AccelPair` (the low byte
+/// is zero), where one LSB of the 8-bit value is 15.616 mg at ±2 g, 31.232 mg at ±4 g,
+/// 62.464 mg at ±8 g and 124.928 mg at ±16 g.
+#[derive(Clone, Copy, PartialEq, Default, Debug)]
+pub enum FifoSample {
+    #[default]
+    Empty,
+    Accel(Xl),
+    AccelTemp {
+        xl: Xl,
+        heat: Heat,
+    },
+    AccelQvar {
+        xl: Xl,
+        ah_qvar: AhQvar,
+    },
+    AccelPair([Xl; 2]),
+    Timestamp(u32),
+    ConfigChange(CfgChg),
+    Steps(Pedo),
+    MlcResult(MlcFifoResult),
+    MlcFilter(MlcFifoValue),
+    MlcFeature(MlcFifoValue),
+    FsmResult(FsmFifoResult),
+    Unknown {
+        tag: u8,
+        raw: [u8; 6],
+    },
+}
+
+impl FifoSample {
+    /// Returns the accelerometer samples carried by the record, in record order.
+    ///
+    /// The slice is empty for records without acceleration and holds two samples for
+    /// `AccelPair`.
+    pub fn xl(&self) -> &[Xl] {
+        match self {
+            FifoSample::Accel(xl)
+            | FifoSample::AccelTemp { xl, .. }
+            | FifoSample::AccelQvar { xl, .. } => core::slice::from_ref(xl),
+            FifoSample::AccelPair(xl) => xl,
+            _ => &[],
+        }
+    }
+
+    /// Returns the number of significant bits of the accelerometer samples, `None` for
+    /// records without acceleration.
+    pub fn resolution_bits(&self) -> Option<u8> {
+        match self {
+            FifoSample::Accel(_) => Some(16),
+            FifoSample::AccelTemp { .. } | FifoSample::AccelQvar { .. } => Some(12),
+            FifoSample::AccelPair(_) => Some(8),
+            _ => None,
+        }
+    }
+
+    /// Mutable version of [`FifoSample::xl`].
+    pub fn xl_mut(&mut self) -> &mut [Xl] {
+        match self {
+            FifoSample::Accel(xl)
+            | FifoSample::AccelTemp { xl, .. }
+            | FifoSample::AccelQvar { xl, .. } => core::slice::from_mut(xl),
+            FifoSample::AccelPair(xl) => xl,
+            _ => &mut [],
+        }
+    }
+}
+
+fn xl_from_raw(raw: [i16; 3], fs: Fs) -> Xl {
+    let mg = raw.map(|r| match fs {
+        Fs::_2g => from_fs2g_to_mg(r),
+        Fs::_4g => from_fs4g_to_mg(r),
+        Fs::_8g => from_fs8g_to_mg(r),
+        Fs::_16g => from_fs16g_to_mg(r),
+    });
+
+    Xl { mg, raw }
+}
+
+impl FifoRecord {
+    /// Decodes the content of a FIFO record.
+    ///
+    /// # Arguments
+    ///
+    /// - `tag: u8`: The sensor tag (`TAG_SENSOR` field).
+    /// - `raw: &[u8; 6]`: The six FIFO data bytes.
+    /// - `ctx: &FifoContext`: The configuration the record was acquired with.
+    ///
+    /// # Returns
+    ///
+    /// - `FifoSample`: The decoded record.
+    pub fn decode(tag: u8, raw: &[u8; 6], ctx: &FifoContext) -> FifoSample {
+        let Ok(tag_sensor) = FifoSensorTag::try_from(tag) else {
+            return FifoSample::Unknown { tag, raw: *raw };
+        };
+
+        match tag_sensor {
+            FifoSensorTag::FifoEmpty => FifoSample::Empty,
+            FifoSensorTag::XlOnly2xTag | FifoSensorTag::XlOnly2xTag2nd => {
+                // A FIFO sample consists of 2X 8-bits 3-axis XL at ODR/2. As in
+                // `iis2dulpx_fifo_data_get` of ST's iis2dulpx_reg.c, the older sample is in the
+                // first three bytes with XlOnly2xTag and in the last three with XlOnly2xTag2nd.
+                let (older, newer) = if tag_sensor == FifoSensorTag::XlOnly2xTag {
+                    (0, 3)
+                } else {
+                    (3, 0)
+                };
+                let xl = |at: usize| {
+                    let raw = [at, at + 1, at + 2].map(|i| i16::from_le_bytes([0, raw[i]]));
+                    xl_from_raw(raw, ctx.fs)
+                };
+
+                FifoSample::AccelPair([xl(older), xl(newer)])
+            }
+            FifoSensorTag::XlAndQvar | FifoSensorTag::XlTempTag if ctx.xl_only == 0 => {
+                // A FIFO sample consists of 12-bits 3-axis XL + T/AH_QVAR at ODR
+                let xl = [
+                    (((raw[1] as u16) << 12) | ((raw[0] as u16) << 4)) as i16,
+                    (((raw[2] as u16) << 8) | (raw[1] as u16 & 0xF0)) as i16,
+                    (((raw[4] as u16) << 12) | ((raw[3] as u16) << 4)) as i16,
+                ];
+                let xl = xl_from_raw(xl, ctx.fs);
+                let packed = ((((raw[5] as u16) << 8) | (raw[4] as u16 & 0xF0)) as i16) >> 4;
+
+                if tag_sensor == FifoSensorTag::XlTempTag {
+                    let heat = Heat {
+                        raw: packed,
+                        deg_c: from_fifo_lsb_to_celsius(packed),
+                    };
+                    FifoSample::AccelTemp { xl, heat }
+                } else {
+                    let ah_qvar = AhQvar {
+                        raw: packed,
+                        mv: from_fifo_lsb_to_mv(packed),
+                    };
+                    FifoSample::AccelQvar { xl, ah_qvar }
+                }
+            }
+            FifoSensorTag::XlAndQvar | FifoSensorTag::XlTempTag => {
+                // A FIFO sample consists of 16-bits 3-axis XL at ODR
+                let xl = [0, 2, 4].map(|i| i16::from_le_bytes([raw[i], raw[i + 1]]));
+
+                FifoSample::Accel(xl_from_raw(xl, ctx.fs))
+            }
+            FifoSensorTag::TimestampTag => {
+                let timestamp = u32::from_le_bytes([raw[2], raw[3], raw[4], raw[5]]);
+
+                if raw[0] >> 7 == 0 {
+                    return FifoSample::Timestamp(timestamp);
+                }
+
+                let lp_hp = raw[0] & 0x1;
+
+                FifoSample::ConfigChange(CfgChg {
+                    cfg_change: raw[0] >> 7,
+                    odr: Odr::new((raw[0] >> 3) & 0xF, lp_hp),
+                    bw: Bw::try_from((raw[0] >> 1) & 0x3).unwrap_or_default(),
+                    lp_hp,
+                    qvar_en: raw[1] >> 7,
+                    fs: Fs::try_from((raw[1] >> 5) & 0x3).unwrap_or_default(),
+                    dec_ts: DecTs::try_from((raw[1] >> 3) & 0x3).unwrap_or_default(),
+                    odr_xl_batch: BdrXl::try_from(raw[1] & 0x7).unwrap_or_default(),
+                    timestamp,
+                })
+            }
+            FifoSensorTag::StepCounterTag => FifoSample::Steps(Pedo {
+                steps: u16::from_le_bytes([raw[0], raw[1]]) as u32,
+                timestamp: u32::from_le_bytes([raw[2], raw[3], raw[4], raw[5]]),
+            }),
+            FifoSensorTag::MlcResultTag => FifoSample::MlcResult(MlcFifoResult {
+                class: raw[0],
+                index: raw[1],
+                timestamp: u32::from_le_bytes([raw[2], raw[3], raw[4], raw[5]]),
+            }),
+            FifoSensorTag::MlcFilterTag | FifoSensorTag::MlcFeature => {
+                let half = u16::from_le_bytes([raw[0], raw[1]]);
+                let value = MlcFifoValue {
+                    id: u16::from_le_bytes([raw[2], raw[3]]),
+                    raw: half,
+                    value: from_f16_to_f32(half),
+                };
+
+                if tag_sensor == FifoSensorTag::MlcFilterTag {
+                    FifoSample::MlcFilter(value)
+                } else {
+                    FifoSample::MlcFeature(value)
+                }
+            }
+            FifoSensorTag::FsmResultTag => FifoSample::FsmResult(FsmFifoResult {
+                output: raw[0],
+                index: raw[1],
+                timestamp: u32::from_le_bytes([raw[2], raw[3], raw[4], raw[5]]),
+            }),
+        }
+    }
+
+    /// Decodes this record, see [`FifoRecord::decode`].
+    pub fn to_sample(&self, ctx: &FifoContext) -> FifoSample {
+        Self::decode(self.tag, &self.data, ctx)
+    }
+}
+
+/// Returns the rate at which accelerometer samples are batched, `None` if they are not batched
+/// at a fixed rate.
+fn batch_rate_hz(odr: Odr, bdr_xl: BdrXl) -> Option<f32> {
+    let div = match bdr_xl {
+        BdrXl::Odr => 1.0,
+        BdrXl::OdrDiv2 => 2.0,
+        BdrXl::OdrDiv4 => 4.0,
+        BdrXl::OdrDiv8 => 8.0,
+        BdrXl::OdrDiv16 => 16.0,
+        BdrXl::OdrDiv32 => 32.0,
+        BdrXl::OdrDiv64 => 64.0,
+        BdrXl::OdrOff => return None,
+    };
+
+    Some(odr.hz()? / div)
+}
+
+/// Represents a decoded FIFO record with the time of its samples.
+///
+/// # Fields
+///
+/// - `sample: FifoSample`: The decoded record.
+/// - `time: [Option<SensorTime>; 2]`: For accelerometer records, the time of each entry of
+///   `sample.xl()`; for `Timestamp` and `ConfigChange` records, the timestamp in `time[0]`.
+///   `None` when the time cannot be determined yet.
+///
+/// # Description
+///
+/// Produced by [`FifoDecoder::decode_timed`] and [`FifoDecoder::decode_batch`]. Times are on
+/// the 64-bit sensor timeline, extended across counter rollovers.
+#[derive(Clone, Copy, PartialEq, Default, Debug)]
+pub struct TimedSample {
+    pub sample: FifoSample,
+    pub time: [Option<SensorTime>; 2],
+}
+
+impl TimedSample {
+    /// Returns the accelerometer samples of the record as independent timed samples, oldest
+    /// first.
+    pub fn xl_samples(&self) -> impl Iterator<Item = TimedXl> + '_ {
+        let bits = self.sample.resolution_bits().unwrap_or_default();
+
+        self.sample
+            .xl()
+            .iter()
+            .zip(self.time)
+            .map(move |(xl, time)| TimedXl {
+                xl: *xl,
+                time,
+                bits,
+            })
+    }
+}
+
+/// Represents one accelerometer sample with its time.
+///
+/// # Fields
+///
+/// - `xl: Xl`: The acceleration.
+/// - `time: Option<SensorTime>`: The acquisition time, `None` if it cannot be determined yet.
+/// - `bits: u8`: Number of significant bits of `xl.raw`, see [`FifoSample::resolution_bits`].
+///
+/// # Description
+///
+/// Returned by [`TimedSample::xl_samples`], so that the two samples of an `AccelPair` record
+/// can be handled like any other sample.
+#[derive(Clone, Copy, PartialEq, Default, Debug)]
+pub struct TimedXl {
+    pub xl: Xl,
+    pub time: Option<SensorTime>,
+    pub bits: u8,
+}
+
+/// Decodes FIFO records while following the configuration changes they report.
+///
+/// # Description
+///
+/// When `cfg_change_in_fifo` is enabled, the sensor inserts a `TimestampTag` record flagging
+/// each change of ODR, full scale or bandwidth. The decoder starts from the configuration
+/// given at creation, replaces it with the one carried by every `ConfigChange` sample and
+/// converts all subsequent accelerometer samples with the matching sensitivity.
+///
+/// When timestamps are batched (`Batch::dec_ts`), each timestamp record gives the time of the
+/// accelerometer sample that follows it. The samples after a timestamp record are placed one
+/// batching period apart, each entry of an `AccelPair` counting as one sample. The period is
+/// measured between the two last timestamp records when possible, and otherwise derived from
+/// the ODR and `Batch::bdr_xl`. [`FifoDecoder::decode_batch`] additionally interpolates the
+/// samples lying between two timestamp records of the same batch.
+#[derive(Clone, Copy)]
+pub struct FifoDecoder {
+    md: Md,
+    xl_only: u8,
+    bdr_xl: BdrXl,
+    extender: TimestampExtender,
+    anchor: Option<SensorTime>,
+    since_anchor: u32,
+    measured_period: Option<f32>,
+}
+
+impl FifoDecoder {
+    /// Creates a decoder for records acquired with the given sensor, FIFO and batching
+    /// configurations.
+    pub fn new(md: &Md, f_md: &FifoMode, batch: &Batch) -> Self {
+        Self {
+            md: *md,
+            xl_only: f_md.xl_only,
+            bdr_xl: batch.bdr_xl,
+            extender: TimestampExtender::new(),
+            anchor: None,
+            since_anchor: 0,
+            measured_period: None,
+        }
+    }
+
+    /// Returns the sensor mode currently assumed for incoming records.
+    pub fn md(&self) -> Md {
+        self.md
+    }
+
+    /// Returns the decoding context derived from the current configuration.
+    pub fn context(&self) -> FifoContext {
+        FifoContext {
+            fs: self.md.fs,
+            xl_only: self.xl_only,
+        }
+    }
+
+    /// Returns the nominal accelerometer batching period in timestamp ticks, `None` if the
+    /// accelerometer is not batched at a fixed rate.
+    pub fn nominal_period(&self) -> Option<f32> {
+        Some(1_000_000.0 / (batch_rate_hz(self.md.odr, self.bdr_xl)? * TIMESTAMP_TICK_US as f32))
+    }
+
+    /// Returns the period used to place samples after the last timestamp record.
+    fn period(&self) -> Option<f32> {
+        self.measured_period.or_else(|| self.nominal_period())
+    }
+
+    fn set_anchor(&mut self, raw: u32) -> SensorTime {
+        let time = self.extender.extend(raw);
+
+        if let Some(anchor) = self
+            .anchor
+            .filter(|anchor| self.since_anchor > 0 && time > *anchor)
+        {
+            self.measured_period = Some((time.0 - anchor.0) as f32 / self.since_anchor as f32);
+        }
+        self.anchor = Some(time);
+        self.since_anchor = 0;
+
+        time
+    }
+
+    /// Decodes a record, updating the configuration if it reports a change.
+    pub fn decode(&mut self, record: &FifoRecord) -> FifoSample {
+        self.decode_timed(record).sample
+    }
+
+    /// Decodes a record and assigns a time to its samples.
+    pub fn decode_timed(&mut self, record: &FifoRecord) -> TimedSample {
+        let sample = record.to_sample(&self.context());
+        let mut time = [None; 2];
+
+        match sample {
+            FifoSample::Timestamp(raw) => time[0] = Some(self.set_anchor(raw)),
+            FifoSample::ConfigChange(cfg_chg) => {
+                time[0] = Some(self.set_anchor(cfg_chg.timestamp));
+                self.md = cfg_chg.md();
+                self.bdr_xl = cfg_chg.odr_xl_batch;
+                // the period measured so far refers to the previous configuration
+                self.measured_period = None;
+            }
+            _ => {
+                for slot in time.iter_mut().take(sample.xl().len()) {
+                    if let (Some(anchor), Some(period)) = (self.anchor, self.period()) {
+                        let offset = (self.since_anchor as f32 * period) as u64;
+                        *slot = Some(SensorTime(anchor.0 + offset));
+                    }
+                    self.since_anchor += 1;
+                }
+            }
+        }
+
+        TimedSample { sample, time }
+    }
+
+    /// Decodes a batch of records, interpolating sample times between timestamp records.
+    ///
+    /// # Arguments
+    ///
+    /// - `records: &[FifoRecord]`: Records in FIFO order, e.g. filled by `fifo_read_batch`.
+    /// - `out: &mut [TimedSample]`: Destination of the decoded records.
+    ///
+    /// # Returns
+    ///
+    /// - `usize`: The number of records decoded, the minimum of both lengths.
+    ///
+    /// # Description
+    ///
+    /// Records are first decoded as with [`FifoDecoder::decode_timed`]. Then, for every two
+    /// consecutive timestamp records of the batch, the accelerometer samples between them are
+    /// evenly spread over the measured interval, which compensates the sensor clock drift with
+    /// respect to the nominal ODR.
+    pub fn decode_batch(&mut self, records: &[FifoRecord], out: &mut [TimedSample]) -> usize {
+        let count = records.len().min(out.len());
+
+        for (record, timed) in records.iter().zip(out.iter_mut()) {
+            *timed = self.decode_timed(record);
+        }
+
+        let out = &mut out[..count];
+        let mut start: Option<(usize, SensorTime)> = None;
+
+        for i in 0..count {
+            let anchor = match out[i].sample {
+                FifoSample::Timestamp(_) | FifoSample::ConfigChange(_) => out[i].time[0],
+                _ => continue,
+            };
+
+            if let Some(((first, t0), t1)) = start.zip(anchor).filter(|((_, t0), t1)| t1 > t0) {
+                let between = &mut out[first + 1..i];
+                let n: usize = between.iter().map(|timed| timed.sample.xl().len()).sum();
+                let step = (t1.0 - t0.0) as f32 / n.max(1) as f32;
+
+                let mut k = 0;
+                for timed in between {
+                    for slot in timed.time.iter_mut().take(timed.sample.xl().len()) {
+                        *slot = Some(SensorTime(t0.0 + (k as f32 * step) as u64));
+                        k += 1;
+                    }
+                }
+            }
+
+            start = anchor.map(|t| (i, t));
+        }
+
+        count
+    }
+}
+
+impl XlQvarData {
+    /// Extracts the paired sample from an `AccelQvar` FIFO sample, `None` for any other one.
+    pub fn from_fifo(sample: &FifoSample) -> Option<Self> {
+        match *sample {
+            FifoSample::AccelQvar { xl, ah_qvar } => Some(Self { xl, ah_qvar }),
+            _ => None,
+        }
+    }
+}
+
+/// Represents the host constraint used to plan the FIFO configuration.
+///
+/// # Variants
+///
+/// - `MaxLatencyMs`: Maximum time in milliseconds between a sample acquisition and the
+///   watermark interrupt that reports it.
+/// - `WakeupsPerSecond`: Maximum number of watermark interrupts per second.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum FifoBudget {
+    MaxLatencyMs(u32),
+    WakeupsPerSecond(f32),
+}
+
+impl FifoBudget {
+    /// Returns the maximum watermark period in seconds.
+    fn period_s(&self) -> f32 {
+        match self {
+            FifoBudget::MaxLatencyMs(ms) => *ms as f32 / 1000.0,
+            FifoBudget::WakeupsPerSecond(rate) => 1.0 / rate,
+        }
+    }
+}
+
+/// Represents the input of the FIFO planner.
+///
+/// # Fields
+///
+/// - `budget: FifoBudget`: Latency or wake-up constraint.
+/// - `bdr_xl: BdrXl`: Accelerometer batch data rate.
+/// - `allow_2x: bool`: Allows the 2x storage depth, with 8-bit samples, when the 1x depth cannot
+///   hold the samples acquired over the budget.
+/// - `timestamps: bool`: Batches timestamps so that each watermark read holds at least one.
+///
+/// # Description
+///
+/// Samples use the 16-bit format (`xl_only = 1`) with the 1x depth and the 8-bit format with
+/// the 2x depth.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct FifoPlanConfig {
+    pub budget: FifoBudget,
+    pub bdr_xl: BdrXl,
+    pub allow_2x: bool,
+    pub timestamps: bool,
+}
+
+/// Represents a FIFO configuration computed from a [`FifoPlanConfig`].
+///
+/// # Fields
+///
+/// - `mode: FifoMode`: FIFO mode, in stream operation.
+/// - `watermark: u8`: FIFO watermark, in entries.
+/// - `batch: Batch`: Timestamp decimation and accelerometer batch data rate.
+/// - `sample_rate_hz: f32`: Rate at which accelerometer samples are batched.
+/// - `latency_s: f32`: Time needed to reach the watermark.
+/// - `capacity_s: f32`: Time needed to fill the whole FIFO, i.e. the maximum delay the host can
+///   add after the watermark interrupt before samples are lost.
+///
+/// # Description
+///
+/// Built with [`FifoPlan::new`] and applied with `fifo_plan_apply`.
+#[derive(Clone, Copy, Debug)]
+pub struct FifoPlan {
+    pub mode: FifoMode,
+    pub watermark: u8,
+    pub batch: Batch,
+    pub sample_rate_hz: f32,
+    pub latency_s: f32,
+    pub capacity_s: f32,
+}
+
+impl FifoPlan {
+    /// Plans the FIFO configuration for the sensor mode `md`.
+    ///
+    /// # Returns
+    ///
+    /// * `Option<Self>`: `None` if the ODR has no fixed rate, the accelerometer is not batched
+    ///   or the budget is not positive.
+    ///
+    /// # Description
+    ///
+    /// The watermark is the largest number of entries acquired within the budget, including
+    /// the timestamp entries. When it exceeds the largest programmable watermark and
+    /// `allow_2x` is set, the 2x depth is selected. When timestamps are requested, the largest
+    /// decimation that still leaves one timestamp entry per watermark is chosen. If the budget
+    /// is shorter than one sample period, the watermark is 1 and `latency_s` exceeds the budget.
+    pub fn new(md: &Md, config: &FifoPlanConfig) -> Option<Self> {
+        let period_s = config.budget.period_s();
+        if period_s.is_nan() || period_s <= 0.0 {
+            return None;
+        }
+
+        let sample_rate_hz = batch_rate_hz(md.odr, config.bdr_xl)?;
+
+        let max_wtm = FIFO_DEPTH - 1;
+        let xl_entries_1x = sample_rate_hz * period_s;
+        let store = if config.allow_2x && xl_entries_1x > max_wtm as f32 {
+            Store::Fifo2x
+        } else {
+            Store::Fifo1x
+        };
+        let samples_per_entry = match store {
+            Store::Fifo1x => 1.0,
+            Store::Fifo2x => 2.0,
+        };
+        let xl_entries = xl_entries_1x / samples_per_entry;
+
+        let (dec_ts, ts_ratio) = if config.timestamps {
+            [(DecTs::_32, 32.0), (DecTs::_8, 8.0), (DecTs::_1, 1.0)]
+                .into_iter()
+                .find(|(_, n)| *n <= xl_entries)
+                .unwrap_or((DecTs::_1, 1.0))
+        } else {
+            (DecTs::Off, f32::INFINITY)
+        };
+
+        let entry_rate = sample_rate_hz / samples_per_entry * (1.0 + 1.0 / ts_ratio);
+        let watermark = ((entry_rate * period_s) as u32).clamp(1, max_wtm as u32) as u8;
+
+        Some(Self {
+            mode: FifoMode {
+                operation: FifoOperation::StreamMode,
+                store,
+                xl_only: 1,
+                cfg_change_in_fifo: 0,
+            },
+            watermark,
+            batch: Batch {
+                dec_ts,
+                bdr_xl: config.bdr_xl,
+            },
+            sample_rate_hz,
+            latency_s: watermark as f32 / entry_rate,
+            capacity_s: FIFO_DEPTH as f32 / entry_rate,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CTX_12BIT: FifoContext = FifoContext {
+        fs: Fs::_2g,
+        xl_only: 0,
+    };
+
+    const CTX_16BIT: FifoContext = FifoContext {
+        fs: Fs::_2g,
+        xl_only: 1,
+    };
+
+    #[test]
+    fn empty() {
+        let sample = FifoRecord::decode(FifoSensorTag::FifoEmpty as u8, &[0; 6], &CTX_16BIT);
+        assert_eq!(sample, FifoSample::Empty);
+    }
+
+    #[test]
+    fn xl_only_16bit() {
+        let raw = [0x34, 0x12, 0xCC, 0xED, 0x00, 0x40];
+
+        for tag in [FifoSensorTag::XlTempTag, FifoSensorTag::XlAndQvar] {
+            let FifoSample::Accel(xl) = FifoRecord::decode(tag as u8, &raw, &CTX_16BIT) else {
+                panic!("unexpected sample");
+            };
+            assert_eq!(xl.raw, [0x1234, -0x1234, 0x4000]);
+            assert_eq!(xl.mg[2], from_fs2g_to_mg(0x4000));
+        }
+    }
+
+    #[test]
+    fn xl_temp_12bit() {
+        // x = 0x123, y = 0x456, z = 0xFED (negative), t = 0x010
+        let raw = [0x23, 0x61, 0x45, 0xED, 0x0F, 0x01];
+
+        let FifoSample::AccelTemp { xl, heat } =
+            FifoRecord::decode(FifoSensorTag::XlTempTag as u8, &raw, &CTX_12BIT)
+        else {
+            panic!("unexpected sample");
+        };
+        assert_eq!(xl.raw, [0x1230, 0x4560, 0xFED0_u16 as i16]);
+        assert_eq!(heat.raw, 0x010);
+        assert_eq!(heat.deg_c, from_fifo_lsb_to_celsius(0x010));
+    }
+
+    #[test]
+    fn xl_qvar_12bit() {
+        // x = 0x800 (most negative), y = 0x7FF, z = 0x000, qvar = 0xFFF (-1)
+        let raw = [0x00, 0xF8, 0x7F, 0x00, 0xF0, 0xFF];
+
+        let FifoSample::AccelQvar { xl, ah_qvar } =
+            FifoRecord::decode(FifoSensorTag::XlAndQvar as u8, &raw, &CTX_12BIT)
+        else {
+            panic!("unexpected sample");
+        };
+        assert_eq!(xl.raw, [i16::MIN, 0x7FF0, 0]);
+        assert_eq!(ah_qvar.raw, -1);
+        assert_eq!(ah_qvar.mv, from_fifo_lsb_to_mv(-1));
+    }
+
+    #[test]
+    fn xl_pair_8bit() {
+        // device lying flat at ±2 g: about +1 g on Z for both samples
+        let raw = [0x00, 0x00, 0x40, 0xFF, 0x01, 0x41];
+        let first = [0, 0, 0x4000];
+        let second = [-0x0100, 0x0100, 0x4100];
+
+        for ctx in [CTX_12BIT, CTX_16BIT] {
+            let sample = FifoRecord::decode(FifoSensorTag::XlOnly2xTag as u8, &raw, &ctx);
+            assert_eq!(sample.resolution_bits(), Some(8));
+            let FifoSample::AccelPair(xl) = sample else {
+                panic!("unexpected sample");
+            };
+            assert_eq!([xl[0].raw, xl[1].raw], [first, second]);
+            let expected = [[0.0, 0.0, 999.424], [-15.616, 15.616, 1015.04]];
+            for (xl, expected) in xl.iter().zip(expected) {
+                for (mg, expected) in xl.mg.iter().zip(expected) {
+                    assert!((mg - expected).abs() < 1e-3);
+                }
+            }
+        }
+
+        let ctx = FifoContext {
+            fs: Fs::_16g,
+            xl_only: 1,
+        };
+        let sample = FifoRecord::decode(FifoSensorTag::XlOnly2xTag as u8, &raw, &ctx);
+        assert!((sample.xl()[0].mg[2] - 7995.392).abs() < 1e-3);
+    }
+
+    #[test]
+    fn xl_pair_8bit_2nd_tag() {
+        // the last three bytes hold the older sample
+        let raw = [0xFF, 0x01, 0x41, 0x00, 0x00, 0x40];
+
+        let sample = FifoRecord::decode(FifoSensorTag::XlOnly2xTag2nd as u8, &raw, &CTX_16BIT);
+        assert_eq!(sample.resolution_bits(), Some(8));
+        let FifoSample::AccelPair(xl) = sample else {
+            panic!("unexpected sample");
+        };
+        assert_eq!(xl[0].raw, [0, 0, 0x4000]);
+        assert_eq!(xl[1].raw, [-0x0100, 0x0100, 0x4100]);
+    }
+
+    #[test]
+    fn timestamp() {
+        let raw = [0x00, 0x00, 0x78, 0x56, 0x34, 0x12];
+
+        let sample = FifoRecord::decode(FifoSensorTag::TimestampTag as u8, &raw, &CTX_16BIT);
+        assert_eq!(sample, FifoSample::Timestamp(0x1234_5678));
+    }
+
+    #[test]
+    fn config_change() {
+        // cfg_change = 1, odr = 0x6, bw = 2, lp_hp = 1, qvar_en = 1, fs = 1, dec_ts = 2, batch = 3
+        let raw = [0b1011_0101, 0b1011_0011, 0x01, 0x00, 0x00, 0x80];
+
+        let FifoSample::ConfigChange(cfg) =
+            FifoRecord::decode(FifoSensorTag::TimestampTag as u8, &raw, &CTX_16BIT)
+        else {
+            panic!("unexpected sample");
+        };
+        assert_eq!(cfg.cfg_change, 1);
+        assert_eq!(cfg.odr, Odr::_25hzHp);
+        assert_eq!(cfg.bw, Bw::OdrDiv8);
+        assert_eq!(cfg.lp_hp, 1);
+        assert_eq!(cfg.qvar_en, 1);
+        assert_eq!(cfg.fs, Fs::_4g);
+        assert_eq!(cfg.dec_ts, DecTs::_8);
+        assert_eq!(cfg.odr_xl_batch, BdrXl::OdrDiv8);
+        assert_eq!(cfg.timestamp, 0x8000_0001);
+    }
+
+    #[test]
+    fn step_counter() {
+        let raw = [0x2C, 0x01, 0x10, 0x27, 0x00, 0x00];
+
+        let sample = FifoRecord::decode(FifoSensorTag::StepCounterTag as u8, &raw, &CTX_16BIT);
+        assert_eq!(
+            sample,
+            FifoSample::Steps(Pedo {
+                steps: 300,
+                timestamp: 10_000,
+            })
+        );
+    }
+
+    #[test]
+    fn mlc_fsm_results() {
+        let raw = [3, 1, 0x10, 0x27, 0, 0];
+
+        let sample = FifoRecord::decode(FifoSensorTag::MlcResultTag as u8, &raw, &CTX_16BIT);
+        assert_eq!(
+            sample,
+            FifoSample::MlcResult(MlcFifoResult {
+                index: 1,
+                class: 3,
+                timestamp: 10_000,
+            })
+        );
+
+        let raw = [0x20, 7, 0x10, 0x27, 0, 0];
+
+        let sample = FifoRecord::decode(FifoSensorTag::FsmResultTag as u8, &raw, &CTX_16BIT);
+        assert_eq!(
+            sample,
+            FifoSample::FsmResult(FsmFifoResult {
+                index: 7,
+                output: 0x20,
+                timestamp: 10_000,
+            })
+        );
+    }
+
+    #[test]
+    fn mlc_filter_feature() {
+        // -2.5 in half precision, id 0x0102
+        let raw = [0x00, 0xC1, 0x02, 0x01, 0, 0];
+        let value = MlcFifoValue {
+            id: 0x0102,
+            raw: 0xC100,
+            value: -2.5,
+        };
+
+        let sample = FifoRecord::decode(FifoSensorTag::MlcFilterTag as u8, &raw, &CTX_16BIT);
+        assert_eq!(sample, FifoSample::MlcFilter(value));
+
+        let sample = FifoRecord::decode(FifoSensorTag::MlcFeature as u8, &raw, &CTX_16BIT);
+        assert_eq!(sample, FifoSample::MlcFeature(value));
+    }
+
+    #[test]
+    fn half_float() {
+        assert_eq!(from_f16_to_f32(0x0000), 0.0);
+        assert_eq!(from_f16_to_f32(0x3C00), 1.0);
+        assert_eq!(from_f16_to_f32(0x7BFF), 65504.0);
+        assert_eq!(from_f16_to_f32(0x0001), 5.960_464_5e-8);
+        assert_eq!(from_f16_to_f32(0xFC00), f32::NEG_INFINITY);
+        assert!(from_f16_to_f32(0x7E00).is_nan());
+    }
+
+    #[test]
+    fn invalid_tag() {
+        let sample = FifoRecord::decode(0x07, &[0xAA; 6], &CTX_16BIT);
+        assert_eq!(
+            sample,
+            FifoSample::Unknown {
+                tag: 0x07,
+                raw: [0xAA; 6],
+            }
+        );
+    }
+
+    #[test]
+    fn record_from_bytes() {
+        // TAG_SENSOR is in bits 7:3 of FIFO_DATA_OUT_TAG, bits 2:0 are ignored.
+        let tag = (FifoSensorTag::TimestampTag as u8) << 3 | 0x05;
+        let record = FifoRecord::from_bytes(&[tag, 1, 2, 3, 4, 5, 6]);
+
+        assert!(record.sensor_tag() == FifoSensorTag::TimestampTag);
+        assert_eq!(record.data, [1, 2, 3, 4, 5, 6]);
+    }
+
+    #[test]
+    fn xl_accessor() {
+        let raw = [0x01, 0x7F, 0x80, 0xFF, 0x00, 0x40];
+
+        let mut sample = FifoRecord::decode(FifoSensorTag::XlOnly2xTag as u8, &raw, &CTX_16BIT);
+        assert_eq!(sample.xl().len(), 2);
+        sample.xl_mut()[1].raw[0] = 0;
+        assert_eq!(sample.xl()[1].raw, [0, 0, 0x4000]);
+
+        let sample = FifoRecord::decode(FifoSensorTag::TimestampTag as u8, &raw, &CTX_16BIT);
+        assert!(sample.xl().is_empty());
+    }
+
+    #[test]
+    fn xl_qvar_from_fifo() {
+        let raw = [0x00, 0xF8, 0x7F, 0x00, 0xF0, 0xFF];
+
+        let sample = FifoRecord::decode(FifoSensorTag::XlAndQvar as u8, &raw, &CTX_12BIT);
+        let data = XlQvarData::from_fifo(&sample).unwrap();
+        assert_eq!(data.ah_qvar.raw, -1);
+
+        let sample = FifoRecord::decode(FifoSensorTag::XlTempTag as u8, &raw, &CTX_12BIT);
+        assert!(XlQvarData::from_fifo(&sample).is_none());
+    }
+
+    #[test]
+    fn decoder_follows_config_change() {
+        let md = Md {
+            odr: Odr::_100hzLp,
+            fs: Fs::_2g,
+            bw: Bw::OdrDiv2,
+        };
+        let f_md = FifoMode {
+            xl_only: 1,
+            ..Default::default()
+        };
+        let mut decoder = FifoDecoder::new(&md, &f_md, &Batch::default());
+
+        let xl = FifoRecord {
+            tag: FifoSensorTag::XlTempTag as u8,
+            data: [0x00, 0x10, 0x00, 0x00, 0x00, 0x00],
+        };
+        let FifoSample::Accel(before) = decoder.decode(&xl) else {
+            panic!("unexpected sample");
+        };
+        assert_eq!(before.mg[0], from_fs2g_to_mg(0x1000));
+
+        // odr = 0x8, lp_hp = 1, bw = 1, fs = 3 (16g)
+        let cfg = FifoRecord {
+            tag: FifoSensorTag::TimestampTag as u8,
+            data: [0b1100_0011, 0b0110_0000, 0, 0, 0, 0],
+        };
+        assert!(matches!(decoder.decode(&cfg), FifoSample::ConfigChange(_)));
+        assert_eq!(
+            decoder.md(),
+            Md {
+                odr: Odr::_100hzHp,
+                fs: Fs::_16g,
+                bw: Bw::OdrDiv4,
+            }
+        );
+
+        let FifoSample::Accel(after) = decoder.decode(&xl) else {
+            panic!("unexpected sample");
+        };
+        assert_eq!(after.raw, before.raw);
+        assert_eq!(after.mg[0], from_fs16g_to_mg(0x1000));
+    }
+
+    fn ts_record(raw: u32) -> FifoRecord {
+        let b = raw.to_le_bytes();
+        FifoRecord {
+            tag: FifoSensorTag::TimestampTag as u8,
+            data: [0, 0, b[0], b[1], b[2], b[3]],
+        }
+    }
+
+    const XL_RECORD: FifoRecord = FifoRecord {
+        tag: FifoSensorTag::XlTempTag as u8,
+        data: [0; 6],
+    };
+
+    fn timed_decoder() -> FifoDecoder {
+        let md = Md {
+            odr: Odr::_100hzLp,
+            fs: Fs::_2g,
+            bw: Bw::OdrDiv2,
+        };
+        let f_md = FifoMode {
+            xl_only: 1,
+            ..Default::default()
+        };
+        let batch = Batch {
+            bdr_xl: BdrXl::OdrDiv2,
+            ..Default::default()
+        };
+        FifoDecoder::new(&md, &f_md, &batch)
+    }
+
+    #[test]
+    fn decoder_nominal_then_measured_period() {
+        let mut decoder = timed_decoder();
+        assert_eq!(decoder.nominal_period(), Some(2000.0));
+
+        assert_eq!(decoder.decode_timed(&XL_RECORD).time, [None; 2]);
+
+        let ts = decoder.decode_timed(&ts_record(10_000));
+        assert_eq!(ts.time[0], Some(SensorTime(10_000)));
+
+        let first = decoder.decode_timed(&XL_RECORD);
+        let second = decoder.decode_timed(&XL_RECORD);
+        assert_eq!(first.time, [Some(SensorTime(10_000)), None]);
+        assert_eq!(second.time, [Some(SensorTime(12_000)), None]);
+
+        // two samples in 4100 ticks: the sensor clock runs slightly slower
+        decoder.decode_timed(&ts_record(14_100));
+        decoder.decode_timed(&XL_RECORD);
+        let next = decoder.decode_timed(&XL_RECORD);
+        assert_eq!(next.time[0], Some(SensorTime(16_150)));
+    }
+
+    #[test]
+    fn decoder_pair_counts_two_samples() {
+        let mut decoder = timed_decoder();
+        let pair = FifoRecord {
+            tag: FifoSensorTag::XlOnly2xTag as u8,
+            data: [0; 6],
+        };
+
+        decoder.decode_timed(&ts_record(0));
+        let timed = decoder.decode_timed(&pair);
+        assert!(matches!(timed.sample, FifoSample::AccelPair(_)));
+        assert_eq!(timed.time, [Some(SensorTime(0)), Some(SensorTime(2000))]);
+
+        let mut samples = timed.xl_samples();
+        let first = samples.next().unwrap();
+        let second = samples.next().unwrap();
+        assert!(samples.next().is_none());
+        assert_eq!((first.time, first.bits), (Some(SensorTime(0)), 8));
+        assert_eq!((second.time, second.bits), (Some(SensorTime(2000)), 8));
+
+        let timed = decoder.decode_timed(&XL_RECORD);
+        assert_eq!(timed.time[0], Some(SensorTime(4000)));
+    }
+
+    #[test]
+    fn decoder_batch_interpolates() {
+        let mut decoder = timed_decoder();
+        let records = [
+            ts_record(u32::MAX - 999),
+            XL_RECORD,
+            XL_RECORD,
+            XL_RECORD,
+            XL_RECORD,
+            ts_record(3000),
+            XL_RECORD,
+        ];
+        let mut out = [TimedSample::default(); 8];
+
+        assert_eq!(decoder.decode_batch(&records, &mut out), records.len());
+
+        let start = u32::MAX as u64 - 999;
+        let times: [_; 4] = core::array::from_fn(|i| out[i + 1].time[0]);
+        assert_eq!(
+            times,
+            [
+                Some(SensorTime(start)),
+                Some(SensorTime(start + 1000)),
+                Some(SensorTime(start + 2000)),
+                Some(SensorTime(start + 3000)),
+            ]
+        );
+        assert_eq!(out[5].time[0], Some(SensorTime(start + 4000)));
+        assert_eq!(out[6].time[0], Some(SensorTime(start + 4000)));
+        assert_eq!(out[7], TimedSample::default());
+    }
+
+    const MD_100HZ: Md = Md {
+        odr: Odr::_100hzLp,
+        fs: Fs::_2g,
+        bw: Bw::OdrDiv2,
+    };
+
+    #[test]
+    fn plan_latency() {
+        let config = FifoPlanConfig {
+            budget: FifoBudget::MaxLatencyMs(500),
+            bdr_xl: BdrXl::Odr,
+            allow_2x: false,
+            timestamps: true,
+        };
+
+        let plan = FifoPlan::new(&MD_100HZ, &config).unwrap();
+        assert_eq!(plan.mode.store, Store::Fifo1x);
+        assert_eq!(plan.batch.dec_ts, DecTs::_32);
+        // 50 samples and 1.5625 timestamps in 500 ms
+        assert_eq!(plan.watermark, 51);
+        assert!(plan.latency_s <= 0.5);
+        assert!((plan.capacity_s - 1.2412).abs() < 1e-3);
+    }
+
+    #[test]
+    fn plan_wakeups_2x() {
+        let mut config = FifoPlanConfig {
+            budget: FifoBudget::WakeupsPerSecond(0.5),
+            bdr_xl: BdrXl::Odr,
+            allow_2x: false,
+            timestamps: false,
+        };
+
+        let plan = FifoPlan::new(&MD_100HZ, &config).unwrap();
+        assert_eq!(plan.mode.store, Store::Fifo1x);
+        assert_eq!(plan.watermark, FIFO_DEPTH - 1);
+        assert!(plan.latency_s < 2.0);
+
+        config.allow_2x = true;
+        let plan = FifoPlan::new(&MD_100HZ, &config).unwrap();
+        assert_eq!(plan.mode.store, Store::Fifo2x);
+        assert_eq!(plan.watermark, 100);
+        assert_eq!(plan.batch.dec_ts, DecTs::Off);
+        assert!((plan.capacity_s - 2.56).abs() < 1e-3);
+    }
+
+    #[test]
+    fn plan_invalid() {
+        let config = FifoPlanConfig {
+            budget: FifoBudget::MaxLatencyMs(0),
+            bdr_xl: BdrXl::Odr,
+            allow_2x: false,
+            timestamps: false,
+        };
+        assert!(FifoPlan::new(&MD_100HZ, &config).is_none());
+
+        let md = Md {
+            odr: Odr::Off,
+            ..MD_100HZ
+        };
+        let config = FifoPlanConfig {
+            budget: FifoBudget::MaxLatencyMs(100),
+            ..config
+        };
+        assert!(FifoPlan::new(&md, &config).is_none());
+    }
+}
@@ -0,0 +1,386 @@
+//! Digital filters for acceleration samples.
+//!
+//! The on-chip `Bw` setting only provides a fixed anti-aliasing low-pass stage. This module
+//! adds lightweight, `no_std` filters that can be run on the host MCU over the samples
+//! returned by `xl_data_get` and `fifo_data_get`:
+//!
+//! * [`LowPass`]: first-order IIR low-pass filter.
+//! * [`HighPass`]: first-order IIR high-pass filter, typically used for gravity removal.
+//! * [`MovingAverage`]: FIR moving-average filter over a fixed window.
+//!
+//! Filters operate on a single channel and can be chained with [`Filter::chain`]. An
+//! [`AxisFilter`] runs one instance of a (possibly chained) filter per axis and can be
+//! attached directly to [`XlData`] and [`FifoSample`].
+//!
+//! IIR cutoffs are expressed in Hz and converted using the sampling frequency of the active
+//! [`Odr`].
+
+use super::fifo::FifoSample;
+use super::register::main::{Odr, XlData};
+
+const PI: f32 = core::f32::consts::PI;
+
+/// Common interface implemented by all single-channel filters.
+pub trait Filter {
+    /// Processes one input sample and returns the filtered value.
+    fn process(&mut self, input: f32) -> f32;
+
+    /// Clears the internal state. The next sample re-initializes the filter.
+    fn reset(&mut self);
+
+    /// Chains `next` after `self`: the output of `self` is fed into `next`.
+    fn chain<F: Filter>(self, next: F) -> Chain<Self, F>
+    where
+        Self: Sized,
+    {
+        Chain {
+            first: self,
+            second: next,
+        }
+    }
+}
+
+/// Two filters connected in series.
+///
+/// # Fields
+///
+/// - `first`: Filter applied to the input sample.
+/// - `second`: Filter applied to the output of `first`.
+///
+/// # Description
+///
+/// Built through [`Filter::chain`]; chains can be nested to build pipelines of any length.
+#[derive(Clone, Copy, Debug)]
+pub struct Chain<A, B> {
+    pub first: A,
+    pub second: B,
+}
+
+impl<A: Filter, B: Filter> Filter for Chain<A, B> {
+    fn process(&mut self, input: f32) -> f32 {
+        let out = self.first.process(input);
+        self.second.process(out)
+    }
+
+    fn reset(&mut self) {
+        self.first.reset();
+        self.second.reset();
+    }
+}
+
+/// Returns the RC time constant and sampling period for a first-order filter.
+///
+/// `None` is returned when the cutoff is not positive or not below the Nyquist frequency.
+fn rc_dt(cutoff_hz: f32, sample_rate_hz: f32) -> Option<(f32, f32)> {
+    if cutoff_hz <= 0.0 || sample_rate_hz <= 0.0 || cutoff_hz >= sample_rate_hz / 2.0 {
+        return None;
+    }
+
+    Some((1.0 / (2.0 * PI * cutoff_hz), 1.0 / sample_rate_hz))
+}
+
+/// First-order IIR low-pass filter.
+///
+/// # Description
+///
+/// Implements `y[n] = y[n-1] + alpha * (x[n] - y[n-1])` with
+/// `alpha = dt / (RC + dt)`. The first sample after creation or [`Filter::reset`] is passed
+/// through unchanged to avoid a start-up transient.
+#[derive(Clone, Copy, Debug)]
+pub struct LowPass {
+    alpha: f32,
+    state: Option<f32>,
+}
+
+impl LowPass {
+    /// Creates a low-pass filter from a cutoff and a sampling frequency, both in Hz.
+    ///
+    /// # Returns
+    ///
+    /// * `Option<Self>`: `None` if the cutoff is not in the `(0, sample_rate_hz / 2)` range.
+    pub fn new(cutoff_hz: f32, sample_rate_hz: f32) -> Option<Self> {
+        let (rc, dt) = rc_dt(cutoff_hz, sample_rate_hz)?;
+
+        Some(Self {
+            alpha: dt / (rc + dt),
+            state: None,
+        })
+    }
+
+    /// Creates a low-pass filter tuned to the sampling frequency of `odr`.
+    ///
+    /// # Returns
+    ///
+    /// * `Option<Self>`: `None` if `odr` has no fixed rate or the cutoff is out of range.
+    pub fn from_odr(cutoff_hz: f32, odr: Odr) -> Option<Self> {
+        Self::new(cutoff_hz, odr.hz()?)
+    }
+}
+
+impl Filter for LowPass {
+    fn process(&mut self, input: f32) -> f32 {
+        let out = match self.state {
+            Some(prev) => prev + self.alpha * (input - prev),
+            None => input,
+        };
+        self.state = Some(out);
+        out
+    }
+
+    fn reset(&mut self) {
+        self.state = None;
+    }
+}
+
+/// First-order IIR high-pass filter.
+///
+/// # Description
+///
+/// Implements `y[n] = alpha * (y[n-1] + x[n] - x[n-1])` with `alpha = RC / (RC + dt)`.
+/// With a cutoff well below the signal band it removes the static gravity component from
+/// acceleration samples. The first sample after creation or [`Filter::reset`] outputs `0.0`.
+#[derive(Clone, Copy, Debug)]
+pub struct HighPass {
+    alpha: f32,
+    state: Option<(f32, f32)>,
+}
+
+impl HighPass {
+    /// Creates a high-pass filter from a cutoff and a sampling frequency, both in Hz.
+    ///
+    /// # Returns
+    ///
+    /// * `Option<Self>`: `None` if the cutoff is not in the `(0, sample_rate_hz / 2)` range.
+    pub fn new(cutoff_hz: f32, sample_rate_hz: f32) -> Option<Self> {
+        let (rc, dt) = rc_dt(cutoff_hz, sample_rate_hz)?;
+
+        Some(Self {
+            alpha: rc / (rc + dt),
+            state: None,
+        })
+    }
+
+    /// Creates a high-pass filter tuned to the sampling frequency of `odr`.
+    ///
+    /// # Returns
+    ///
+    /// * `Option<Self>`: `None` if `odr` has no fixed rate or the cutoff is out of range.
+    pub fn from_odr(cutoff_hz: f32, odr: Odr) -> Option<Self> {
+        Self::new(cutoff_hz, odr.hz()?)
+    }
+}
+
+impl Filter for HighPass {
+    fn process(&mut self, input: f32) -> f32 {
+        let out = match self.state {
+            Some((prev_in, prev_out)) => self.alpha * (prev_out + input - prev_in),
+            None => 0.0,
+        };
+        self.state = Some((input, out));
+        out
+    }
+
+    fn reset(&mut self) {
+        self.state = None;
+    }
+}
+
+/// FIR moving-average filter over the last `N` samples.
+///
+/// # Description
+///
+/// Until `N` samples have been received the output is the average of the samples seen so
+/// far. The running sum is updated incrementally and recomputed from the window each time
+/// the ring index wraps, so rounding errors do not build up on long streams.
+#[derive(Clone, Copy, Debug)]
+pub struct MovingAverage<const N: usize> {
+    window: [f32; N],
+    pos: usize,
+    len: usize,
+    sum: f32,
+}
+
+impl<const N: usize> MovingAverage<N> {
+    /// Creates an empty moving-average filter.
+    pub const fn new() -> Self {
+        assert!(N > 0);
+
+        Self {
+            window: [0.0; N],
+            pos: 0,
+            len: 0,
+            sum: 0.0,
+        }
+    }
+}
+
+impl<const N: usize> Default for MovingAverage<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const N: usize> Filter for MovingAverage<N> {
+    fn process(&mut self, input: f32) -> f32 {
+        if self.len == N {
+            self.sum -= self.window[self.pos];
+        } else {
+            self.len += 1;
+        }
+        self.window[self.pos] = input;
+        self.sum += input;
+        self.pos = (self.pos + 1) % N;
+        if self.pos == 0 {
+            self.sum = self.window.iter().sum();
+        }
+
+        self.sum / self.len as f32
+    }
+
+    fn reset(&mut self) {
+        *self = Self::new();
+    }
+}
+
+/// Runs one filter instance per accelerometer axis.
+///
+/// # Fields
+///
+/// - `axes: [F; 3]`: Filters for the X, Y and Z axes.
+///
+/// # Description
+///
+/// Wraps any [`Filter`], including a [`Chain`], so the same pipeline can be attached to the
+/// milli-g values of [`XlData`] or [`FifoSample`]. Raw values are left untouched.
+#[derive(Clone, Copy, Debug)]
+pub struct AxisFilter<F: Filter> {
+    pub axes: [F; 3],
+}
+
+impl<F: Filter + Clone> AxisFilter<F> {
+    /// Creates a three-axis filter from a single-channel prototype.
+    pub fn new(filter: F) -> Self {
+        Self {
+            axes: [filter.clone(), filter.clone(), filter],
+        }
+    }
+}
+
+impl<F: Filter> AxisFilter<F> {
+    /// Filters one `[x, y, z]` sample in place.
+    pub fn apply(&mut self, mg: &mut [f32; 3]) {
+        for (filter, value) in self.axes.iter_mut().zip(mg.iter_mut()) {
+            *value = filter.process(*value);
+        }
+    }
+
+    /// Filters the milli-g values returned by `xl_data_get`.
+    pub fn apply_xl_data(&mut self, data: &mut XlData) {
+        self.apply(&mut data.mg);
+    }
+
+    /// Filters the accelerometer samples carried by a FIFO record.
+    ///
+    /// # Description
+    ///
+    /// Records without accelerometer data (timestamp, step counter, ...) are ignored. For
+    /// `AccelPair` both samples are filtered, oldest first.
+    pub fn apply_fifo_sample(&mut self, sample: &mut FifoSample) {
+        for xl in sample.xl_mut() {
+            self.apply(&mut xl.mg);
+        }
+    }
+
+    /// Clears the state of all axes.
+    pub fn reset(&mut self) {
+        self.axes.iter_mut().for_each(Filter::reset);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn low_pass_step_response() {
+        let mut filter = LowPass::new(1.0, 100.0).unwrap();
+        assert_eq!(filter.process(0.0), 0.0);
+
+        // After one time constant (RC = 1 / (2 * PI * fc)) a first-order filter reaches 63 %
+        // of the step.
+        let tau = (100.0 / (2.0 * PI)).round() as usize;
+        let out = (0..tau).map(|_| filter.process(1.0)).last().unwrap();
+        assert!((out - 0.63).abs() < 0.02);
+
+        let out = (0..1000).map(|_| filter.process(1.0)).last().unwrap();
+        assert!((out - 1.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn high_pass_step_response() {
+        let mut filter = HighPass::new(1.0, 100.0).unwrap();
+        assert_eq!(filter.process(1000.0), 0.0);
+
+        let out = filter.process(2000.0);
+        assert!(out > 900.0 && out < 1000.0);
+
+        let out = (0..1000).map(|_| filter.process(2000.0)).last().unwrap();
+        assert!(out.abs() < 1e-3);
+    }
+
+    #[test]
+    fn cutoff_range() {
+        assert!(LowPass::new(0.0, 100.0).is_none());
+        assert!(LowPass::new(50.0, 100.0).is_none());
+        assert!(HighPass::new(-1.0, 100.0).is_none());
+        assert!(LowPass::from_odr(1.0, Odr::Off).is_none());
+        assert!(LowPass::from_odr(1.0, Odr::_100hzLp).is_some());
+    }
+
+    #[test]
+    fn moving_average() {
+        let mut filter = MovingAverage::<4>::new();
+        assert_eq!(filter.process(4.0), 4.0);
+        assert_eq!(filter.process(8.0), 6.0);
+        filter.process(0.0);
+        assert_eq!(filter.process(0.0), 3.0);
+        assert_eq!(filter.process(4.0), 3.0);
+
+        filter.reset();
+        assert_eq!(filter.process(1.0), 1.0);
+    }
+
+    #[test]
+    fn moving_average_no_drift() {
+        let mut filter = MovingAverage::<4>::new();
+        for _ in 0..4 {
+            filter.process(1.0e7);
+        }
+
+        // 0.1 is below the resolution of a running sum of 4e7
+        let mut out = 0.0;
+        for _ in 0..4 {
+            out = filter.process(0.1);
+        }
+        assert!((out - 0.1).abs() < 1e-6);
+    }
+
+    #[test]
+    fn chain_and_axes() {
+        let pipeline = MovingAverage::<2>::new().chain(MovingAverage::<2>::new());
+        let mut axes = AxisFilter::new(pipeline);
+
+        let mut mg = [0.0, 4.0, 8.0];
+        axes.apply(&mut mg);
+        assert_eq!(mg, [0.0, 4.0, 8.0]);
+
+        let mut mg = [4.0, 4.0, 0.0];
+        axes.apply(&mut mg);
+        assert_eq!(mg, [1.0, 4.0, 6.0]);
+
+        axes.reset();
+        let mut mg = [2.0, 2.0, 2.0];
+        axes.apply(&mut mg);
+        assert_eq!(mg, [2.0; 3]);
+    }
+}
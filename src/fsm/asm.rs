@@ -0,0 +1,956 @@
+//! FSM program assembler and disassembler.
+//!
+//! An FSM program is a byte string made of a fixed part, a variable data section and the
+//! instructions:
+//!
+//! | Offset | Field                                                                        |
+//! |--------|------------------------------------------------------------------------------|
+//! | 0      | `CONFIG_A`: `NR_THRESH[7:6]`, `NR_MASK[5:4]`, `NR_LTIMER[3:2]`, `NR_TIMER[1:0]` |
+//! | 1      | `CONFIG_B`: program options                                                  |
+//! | 2      | `SIZE`: length of the whole program in bytes                                 |
+//! | 3      | `SETTINGS`: `MASKSEL[7:6]`, `SIGNED[5]`, `R_TAM[4]`, `T_AM[3]`, `IN_SEL[2:0]` |
+//! | 4      | `RP`: reset pointer, offset of the instruction the program restarts from     |
+//! | 5      | `PP`: program pointer, 0 when loaded                                         |
+//! | 6      | Thresholds, 2 bytes each, half-precision little-endian                       |
+//! |        | Masks, 2 bytes each: the mask and its temporary copy                         |
+//! |        | `TC`, timer counter, 1 byte, only if there are timers                        |
+//! |        | Timers, 2 bytes each, in samples, little-endian                              |
+//! |        | Instructions                                                                 |
+//!
+//! Instructions are one byte, possibly followed by parameters. A condition byte holds the
+//! reset condition in its high nibble and the next condition in its low nibble, see
+//! [`FsmCondition`]. The byte values listed by [`FsmCommand`] are commands instead.
+//!
+//! [`FsmSource::assemble`] produces the bytes accepted by
+//! [`FsmProgram::new`](super::FsmProgram::new); [`FsmListing::parse`] decodes a program, e.g.
+//! read back from the FSM memory with `ln_pg_read`.
+//!
+//! Only short timers are supported: the timers of a program, at most [`FSM_MAX_TIMERS`], are
+//! timer 3 and timer 4, tested by the `Ti3` and `Ti4` conditions and set by `Stimer3` and
+//! `Stimer4`. Long timers (`NR_LTIMER`, conditions `Ti1` and `Ti2`) and the `CONFIG_B` options
+//! other than bit 4, such as decimation or hysteresis, which add data to the variable section,
+//! are not supported.
+
+use derive_more::TryFrom;
+
+use super::super::driver::{from_f16_to_f32, from_f32_to_f16};
+use super::FSM_HEADER_LEN;
+
+/// Maximum number of thresholds or masks of a program.
+pub const FSM_MAX_ITEMS: usize = 3;
+
+/// Maximum number of timers of a program: timer 3 and timer 4.
+pub const FSM_MAX_TIMERS: usize = 2;
+
+/// `CONFIG_B` bits supported by the assembler. The other bits enable options (decimation,
+/// hysteresis, previous axis sign, ...) that add data to the variable section.
+const CONFIG_B_SUPPORTED: u8 = 0x10;
+
+/// Represents the conditions tested by an FSM instruction.
+///
+/// # Variants
+///
+/// - `Nop`: No condition, never true.
+/// - `Ti1`..`Ti4`: Timer 1 to 4 elapsed.
+/// - `Gnth1`, `Gnth2`: Any masked axis greater than threshold 1 or 2.
+/// - `Lnth1`, `Lnth2`: All masked axes less than or equal to threshold 1 or 2.
+/// - `Gtth1`: All masked axes greater than threshold 1.
+/// - `Llth2`: All masked axes less than or equal to threshold 2.
+/// - `Grth1`, `Grth2`: Any masked axis greater than threshold 1 or 2, with the opposite sign.
+/// - `Lrth1`, `Lrth2`: All masked axes less than or equal to threshold 1 or 2, with the opposite
+///   sign.
+/// - `Nzc`: Any masked axis crossed zero.
+#[repr(u8)]
+#[derive(Clone, Copy, PartialEq, Eq, Default, Debug, TryFrom)]
+#[try_from(repr)]
+pub enum FsmCondition {
+    #[default]
+    Nop = 0x0,
+    Ti1 = 0x1,
+    Ti2 = 0x2,
+    Ti3 = 0x3,
+    Ti4 = 0x4,
+    Gnth1 = 0x5,
+    Gnth2 = 0x6,
+    Lnth1 = 0x7,
+    Lnth2 = 0x8,
+    Gtth1 = 0x9,
+    Llth2 = 0xA,
+    Grth1 = 0xB,
+    Lrth1 = 0xC,
+    Grth2 = 0xD,
+    Lrth2 = 0xE,
+    Nzc = 0xF,
+}
+
+/// Represents the commands of an FSM program.
+///
+/// # Variants
+///
+/// - `Stop` (00h): Stops the program until it is restarted.
+/// - `Goto` (11h): Jumps to the reset point.
+/// - `Cont` (22h): Jumps to the reset point and sets the interrupt and output.
+/// - `Srp` (33h): Sets the reset point to the next instruction.
+/// - `Crp` (44h): Clears the reset point, back to the first instruction.
+/// - `Setp` (55h): Writes `value` at `offset` of the program.
+/// - `Selma`, `Selmb`, `Selmc` (66h, 77h, 88h): Selects mask A, B or C.
+/// - `Outc` (99h): Copies the temporary mask to the output register and raises the interrupt.
+/// - `Sthr1`, `Sthr2` (AAh, BBh): Sets threshold 1 or 2.
+/// - `Selthr1`, `Selthr3` (CCh, DDh): Selects threshold 1 or 3 as the first threshold.
+/// - `Sisw` (EEh): Swaps the sign of the input.
+/// - `Rel` (FFh): Resets the temporary mask to the selected mask.
+/// - `Ssign0`, `Ssign1` (12h, 13h): Sets unsigned or signed comparisons.
+/// - `Srtam0`, `Srtam1` (14h, 21h): Disables or enables the temporary mask reset on `RP`.
+/// - `Sinmux` (23h): Selects the input.
+/// - `Stimer3`, `Stimer4` (24h, 31h): Sets timer 3 or 4, in samples.
+/// - `Swapmsk` (32h): Swaps the selected and temporary masks.
+/// - `Intor1`, `Intand1` (34h, 41h): Combines conditions on masked axes with OR or AND.
+/// - `Jmp` (42h): Jumps to `true_offset` if `conditions` (reset and next nibbles) holds,
+///   to `false_offset` otherwise.
+/// - `Contrel` (43h): `Cont` followed by `Rel`.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum FsmCommand {
+    Stop,
+    Goto,
+    Cont,
+    Srp,
+    Crp,
+    Setp {
+        offset: u8,
+        value: u8,
+    },
+    Selma,
+    Selmb,
+    Selmc,
+    Outc,
+    Sthr1(f32),
+    Sthr2(f32),
+    Selthr1,
+    Selthr3,
+    Sisw,
+    Rel,
+    Ssign0,
+    Ssign1,
+    Srtam0,
+    Srtam1,
+    Sinmux(u8),
+    Stimer3(u8),
+    Stimer4(u8),
+    Swapmsk,
+    Intor1,
+    Intand1,
+    Jmp {
+        conditions: u8,
+        true_offset: u8,
+        false_offset: u8,
+    },
+    Contrel,
+}
+
+impl FsmCommand {
+    /// Returns the opcode and the number of parameter bytes of the command.
+    fn opcode(&self) -> (u8, usize) {
+        match self {
+            FsmCommand::Stop => (0x00, 0),
+            FsmCommand::Goto => (0x11, 0),
+            FsmCommand::Cont => (0x22, 0),
+            FsmCommand::Srp => (0x33, 0),
+            FsmCommand::Crp => (0x44, 0),
+            FsmCommand::Setp { .. } => (0x55, 2),
+            FsmCommand::Selma => (0x66, 0),
+            FsmCommand::Selmb => (0x77, 0),
+            FsmCommand::Selmc => (0x88, 0),
+            FsmCommand::Outc => (0x99, 0),
+            FsmCommand::Sthr1(_) => (0xAA, 2),
+            FsmCommand::Sthr2(_) => (0xBB, 2),
+            FsmCommand::Selthr1 => (0xCC, 0),
+            FsmCommand::Selthr3 => (0xDD, 0),
+            FsmCommand::Sisw => (0xEE, 0),
+            FsmCommand::Rel => (0xFF, 0),
+            FsmCommand::Ssign0 => (0x12, 0),
+            FsmCommand::Ssign1 => (0x13, 0),
+            FsmCommand::Srtam0 => (0x14, 0),
+            FsmCommand::Srtam1 => (0x21, 0),
+            FsmCommand::Sinmux(_) => (0x23, 1),
+            FsmCommand::Stimer3(_) => (0x24, 1),
+            FsmCommand::Stimer4(_) => (0x31, 1),
+            FsmCommand::Swapmsk => (0x32, 0),
+            FsmCommand::Intor1 => (0x34, 0),
+            FsmCommand::Intand1 => (0x41, 0),
+            FsmCommand::Jmp { .. } => (0x42, 3),
+            FsmCommand::Contrel => (0x43, 0),
+        }
+    }
+
+    /// Returns the parameter bytes of the command.
+    fn params(&self) -> [u8; 3] {
+        match *self {
+            FsmCommand::Setp { offset, value } => [offset, value, 0],
+            FsmCommand::Sthr1(ths) | FsmCommand::Sthr2(ths) => {
+                let [lsb, msb] = from_f32_to_f16(ths).to_le_bytes();
+                [lsb, msb, 0]
+            }
+            FsmCommand::Sinmux(val) | FsmCommand::Stimer3(val) | FsmCommand::Stimer4(val) => {
+                [val, 0, 0]
+            }
+            FsmCommand::Jmp {
+                conditions,
+                true_offset,
+                false_offset,
+            } => [conditions, true_offset, false_offset],
+            _ => [0; 3],
+        }
+    }
+
+    /// Decodes the command starting `code`, returning it with its length in bytes.
+    ///
+    /// `None` if the first byte is not a command, `Some(Err)` if the parameters are missing.
+    fn decode(code: &[u8]) -> Option<Result<(Self, usize), FsmAsmError>> {
+        let param = |index: usize| code.get(index).copied().ok_or(FsmAsmError::Truncated);
+        let with_params = |len: usize, decode: fn(&[u8]) -> Self| {
+            let params = code.get(1..=len).ok_or(FsmAsmError::Truncated)?;
+            Ok((decode(params), len + 1))
+        };
+
+        let command = match *code.first()? {
+            0x00 => FsmCommand::Stop,
+            0x11 => FsmCommand::Goto,
+            0x22 => FsmCommand::Cont,
+            0x33 => FsmCommand::Srp,
+            0x44 => FsmCommand::Crp,
+            0x55 => {
+                return Some(with_params(2, |p| FsmCommand::Setp {
+                    offset: p[0],
+                    value: p[1],
+                }));
+            }
+            0x66 => FsmCommand::Selma,
+            0x77 => FsmCommand::Selmb,
+            0x88 => FsmCommand::Selmc,
+            0x99 => FsmCommand::Outc,
+            0xAA => {
+                return Some(with_params(2, |p| {
+                    FsmCommand::Sthr1(from_f16_to_f32(u16::from_le_bytes([p[0], p[1]])))
+                }));
+            }
+            0xBB => {
+                return Some(with_params(2, |p| {
+                    FsmCommand::Sthr2(from_f16_to_f32(u16::from_le_bytes([p[0], p[1]])))
+                }));
+            }
+            0xCC => FsmCommand::Selthr1,
+            0xDD => FsmCommand::Selthr3,
+            0xEE => FsmCommand::Sisw,
+            0xFF => FsmCommand::Rel,
+            0x12 => FsmCommand::Ssign0,
+            0x13 => FsmCommand::Ssign1,
+            0x14 => FsmCommand::Srtam0,
+            0x21 => FsmCommand::Srtam1,
+            0x23 => return Some(param(1).map(|val| (FsmCommand::Sinmux(val), 2))),
+            0x24 => return Some(param(1).map(|val| (FsmCommand::Stimer3(val), 2))),
+            0x31 => return Some(param(1).map(|val| (FsmCommand::Stimer4(val), 2))),
+            0x32 => FsmCommand::Swapmsk,
+            0x34 => FsmCommand::Intor1,
+            0x41 => FsmCommand::Intand1,
+            0x42 => {
+                return Some(with_params(3, |p| FsmCommand::Jmp {
+                    conditions: p[0],
+                    true_offset: p[1],
+                    false_offset: p[2],
+                }));
+            }
+            0x43 => FsmCommand::Contrel,
+            _ => return None,
+        };
+
+        Some(Ok((command, 1)))
+    }
+}
+
+/// Represents one instruction of an FSM program.
+///
+/// # Variants
+///
+/// - `Condition { reset, next }`: Waits for `next` to move to the next instruction, or for
+///   `reset` to go back to the reset point.
+/// - `Command(FsmCommand)`: Executes a command.
+///
+/// # Description
+///
+/// Some condition pairs share their byte value with a command, e.g. `Ti1`/`Ti2` and
+/// `Ssign0`: they cannot be assembled.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum FsmInstruction {
+    Condition {
+        reset: FsmCondition,
+        next: FsmCondition,
+    },
+    Command(FsmCommand),
+}
+
+impl Default for FsmInstruction {
+    fn default() -> Self {
+        FsmInstruction::Command(FsmCommand::Stop)
+    }
+}
+
+impl FsmInstruction {
+    /// Returns the size of the instruction in bytes.
+    pub fn size(&self) -> usize {
+        match self {
+            FsmInstruction::Condition { .. } => 1,
+            FsmInstruction::Command(command) => 1 + command.opcode().1,
+        }
+    }
+
+    /// Decodes the instruction starting `code`, returning it with its length in bytes.
+    fn decode(code: &[u8]) -> Result<(Self, usize), FsmAsmError> {
+        let first = *code.first().ok_or(FsmAsmError::Truncated)?;
+
+        match FsmCommand::decode(code) {
+            Some(command) => command.map(|(command, len)| (FsmInstruction::Command(command), len)),
+            None => Ok((
+                FsmInstruction::Condition {
+                    reset: FsmCondition::try_from(first >> 4).unwrap_or_default(),
+                    next: FsmCondition::try_from(first & 0x0F).unwrap_or_default(),
+                },
+                1,
+            )),
+        }
+    }
+
+    /// Writes the instruction to `out`, which holds at least `self.size()` bytes.
+    fn encode(&self, out: &mut [u8]) -> Result<(), FsmAsmError> {
+        match self {
+            FsmInstruction::Condition { reset, next } => {
+                let byte = ((*reset as u8) << 4) | *next as u8;
+                if FsmCommand::decode(&[byte, 0, 0, 0]).is_some() {
+                    return Err(FsmAsmError::AmbiguousCondition(byte));
+                }
+                out[0] = byte;
+            }
+            FsmInstruction::Command(command) => {
+                let (opcode, len) = command.opcode();
+                out[0] = opcode;
+                out[1..=len].copy_from_slice(&command.params()[..len]);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Represents the `SETTINGS` byte of a program.
+///
+/// # Fields
+///
+/// - `mask_sel: u8`: Initially selected mask, 0 for mask A.
+/// - `signed: bool`: Signed comparisons with the thresholds.
+/// - `r_tam: bool`: Resets the temporary mask when the program goes back to the reset point.
+/// - `t_am: bool`: Uses the temporary mask.
+/// - `in_sel: u8`: Input of the program, 0 for the accelerometer.
+#[derive(Clone, Copy, PartialEq, Eq, Default, Debug)]
+pub struct FsmSettings {
+    pub mask_sel: u8,
+    pub signed: bool,
+    pub r_tam: bool,
+    pub t_am: bool,
+    pub in_sel: u8,
+}
+
+impl FsmSettings {
+    fn from_bits(bits: u8) -> Self {
+        Self {
+            mask_sel: bits >> 6,
+            signed: bits & 0x20 != 0,
+            r_tam: bits & 0x10 != 0,
+            t_am: bits & 0x08 != 0,
+            in_sel: bits & 0x07,
+        }
+    }
+
+    fn into_bits(self) -> Option<u8> {
+        if self.mask_sel > 2 || self.in_sel > 7 {
+            return None;
+        }
+
+        Some(
+            (self.mask_sel << 6)
+                | ((self.signed as u8) << 5)
+                | ((self.r_tam as u8) << 4)
+                | ((self.t_am as u8) << 3)
+                | self.in_sel,
+        )
+    }
+}
+
+/// Represents a mask of a program.
+///
+/// # Fields
+///
+/// - `mask: u8`: Axes and signs tested by the conditions, from bit 7 to bit 0: `+X`, `-X`,
+///   `+Y`, `-Y`, `+Z`, `-Z`, `+V`, `-V`.
+/// - `temporary: u8`: Working copy of the mask, usually 0 when loaded.
+#[derive(Clone, Copy, PartialEq, Eq, Default, Debug)]
+pub struct FsmMask {
+    pub mask: u8,
+    pub temporary: u8,
+}
+
+/// Represents the errors of the assembler and the disassembler.
+///
+/// # Variants
+///
+/// - `TooManyItems`: More than [`FSM_MAX_ITEMS`] thresholds or masks, or more than
+///   [`FSM_MAX_TIMERS`] timers.
+/// - `InvalidSettings`: `mask_sel` or `in_sel` out of range.
+/// - `AmbiguousCondition(u8)`: Condition pair encoded as the given command byte.
+/// - `InvalidResetPoint`: The reset point is not the start of an instruction.
+/// - `TooLong(usize)`: The program needs the given number of bytes, more than 255.
+/// - `BufferTooSmall`: The output buffer cannot hold the program.
+/// - `Truncated`: The program is shorter than its header or an instruction lacks parameters.
+/// - `UndeclaredTimer`: A `Ti3`/`Ti4` condition or a `Stimer3`/`Stimer4` command refers to a
+///   timer the program does not declare.
+/// - `Unsupported`: The program uses long timers or unsupported `CONFIG_B` options.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum FsmAsmError {
+    TooManyItems,
+    InvalidSettings,
+    AmbiguousCondition(u8),
+    UndeclaredTimer,
+    InvalidResetPoint,
+    TooLong(usize),
+    BufferTooSmall,
+    Truncated,
+    Unsupported,
+}
+
+/// An FSM program described with typed values.
+///
+/// # Fields
+///
+/// - `config_b: u8`: `CONFIG_B` options. Only bit 4 is supported, the other options add data
+///   to the variable section.
+/// - `settings: FsmSettings`: The `SETTINGS` byte.
+/// - `thresholds: &[f32]`: Thresholds 1 to 3, in g for the accelerometer.
+/// - `masks: &[FsmMask]`: Masks A to C.
+/// - `timers: &[u16]`: Timer 3 and timer 4, in samples at the FSM data rate.
+/// - `reset_point: usize`: Index of the instruction the program restarts from.
+/// - `instructions: &[FsmInstruction]`: The code.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct FsmSource<'a> {
+    pub config_b: u8,
+    pub settings: FsmSettings,
+    pub thresholds: &'a [f32],
+    pub masks: &'a [FsmMask],
+    pub timers: &'a [u16],
+    pub reset_point: usize,
+    pub instructions: &'a [FsmInstruction],
+}
+
+impl FsmSource<'_> {
+    /// Returns the size of the variable data section in bytes.
+    fn data_len(&self) -> usize {
+        let timers = match self.timers.len() {
+            0 => 0,
+            n => 1 + 2 * n,
+        };
+
+        2 * self.thresholds.len() + 2 * self.masks.len() + timers
+    }
+
+    /// Checks that a condition refers to a declared short timer.
+    fn check_condition(&self, condition: FsmCondition) -> Result<(), FsmAsmError> {
+        let timer = match condition {
+            FsmCondition::Ti1 | FsmCondition::Ti2 => return Err(FsmAsmError::Unsupported),
+            FsmCondition::Ti3 => 0,
+            FsmCondition::Ti4 => 1,
+            _ => return Ok(()),
+        };
+
+        self.check_timer(timer)
+    }
+
+    /// Checks that `timers[index]` is declared.
+    fn check_timer(&self, index: usize) -> Result<(), FsmAsmError> {
+        if index < self.timers.len() {
+            Ok(())
+        } else {
+            Err(FsmAsmError::UndeclaredTimer)
+        }
+    }
+
+    /// Checks the timers used by an instruction.
+    fn check_instruction(&self, instruction: &FsmInstruction) -> Result<(), FsmAsmError> {
+        let conditions = match instruction {
+            FsmInstruction::Condition { reset, next } => [*reset, *next],
+            FsmInstruction::Command(FsmCommand::Jmp { conditions, .. }) => [
+                FsmCondition::try_from(conditions >> 4).unwrap_or_default(),
+                FsmCondition::try_from(conditions & 0x0F).unwrap_or_default(),
+            ],
+            FsmInstruction::Command(FsmCommand::Stimer3(_)) => return self.check_timer(0),
+            FsmInstruction::Command(FsmCommand::Stimer4(_)) => return self.check_timer(1),
+            FsmInstruction::Command(_) => return Ok(()),
+        };
+
+        conditions
+            .into_iter()
+            .try_for_each(|condition| self.check_condition(condition))
+    }
+
+    /// Assembles the program into `out`.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<usize, FsmAsmError>`: The size of the program, or the first error found.
+    pub fn assemble(&self, out: &mut [u8]) -> Result<usize, FsmAsmError> {
+        if self.thresholds.len() > FSM_MAX_ITEMS
+            || self.masks.len() > FSM_MAX_ITEMS
+            || self.timers.len() > FSM_MAX_TIMERS
+        {
+            return Err(FsmAsmError::TooManyItems);
+        }
+        if self.config_b & !CONFIG_B_SUPPORTED != 0 {
+            return Err(FsmAsmError::Unsupported);
+        }
+        self.instructions
+            .iter()
+            .try_for_each(|instruction| self.check_instruction(instruction))?;
+        let settings = self
+            .settings
+            .into_bits()
+            .ok_or(FsmAsmError::InvalidSettings)?;
+
+        let code_start = FSM_HEADER_LEN + self.data_len();
+        let reset_point = code_start
+            + self
+                .instructions
+                .get(..self.reset_point)
+                .ok_or(FsmAsmError::InvalidResetPoint)?
+                .iter()
+                .map(FsmInstruction::size)
+                .sum::<usize>();
+        let size = code_start
+            + self
+                .instructions
+                .iter()
+                .map(FsmInstruction::size)
+                .sum::<usize>();
+        if size > u8::MAX as usize {
+            return Err(FsmAsmError::TooLong(size));
+        }
+        let out = out.get_mut(..size).ok_or(FsmAsmError::BufferTooSmall)?;
+
+        out[0] = ((self.thresholds.len() as u8) << 6)
+            | ((self.masks.len() as u8) << 4)
+            | self.timers.len() as u8;
+        out[1] = self.config_b;
+        out[2] = size as u8;
+        out[3] = settings;
+        out[4] = reset_point as u8;
+        out[5] = 0;
+
+        let mut pos = FSM_HEADER_LEN;
+        for ths in self.thresholds {
+            out[pos..pos + 2].copy_from_slice(&from_f32_to_f16(*ths).to_le_bytes());
+            pos += 2;
+        }
+        for mask in self.masks {
+            out[pos..pos + 2].copy_from_slice(&[mask.mask, mask.temporary]);
+            pos += 2;
+        }
+        if !self.timers.is_empty() {
+            out[pos] = 0;
+            pos += 1;
+        }
+        for timer in self.timers {
+            out[pos..pos + 2].copy_from_slice(&timer.to_le_bytes());
+            pos += 2;
+        }
+        for instruction in self.instructions {
+            instruction.encode(&mut out[pos..])?;
+            pos += instruction.size();
+        }
+
+        Ok(size)
+    }
+}
+
+/// A decoded FSM program.
+///
+/// # Description
+///
+/// Built by [`FsmListing::parse`], which checks the header and the variable data section.
+/// Instructions are decoded on demand, by [`FsmListing::instructions`] or into a caller
+/// buffer by [`FsmListing::source`].
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct FsmListing<'a> {
+    pub config_b: u8,
+    pub settings: FsmSettings,
+    thresholds: [f32; FSM_MAX_ITEMS],
+    masks: [FsmMask; FSM_MAX_ITEMS],
+    timers: [u16; FSM_MAX_TIMERS],
+    counts: [usize; 3],
+    reset_offset: usize,
+    code: &'a [u8],
+}
+
+impl<'a> FsmListing<'a> {
+    /// Decodes the header and the variable data section of a program.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Self, FsmAsmError>`: The listing, or an error if the program is truncated,
+    ///   uses long timers, more than [`FSM_MAX_TIMERS`] timers or unsupported `CONFIG_B`
+    ///   options, or has a reset point outside its code. Bytes after `SIZE` are ignored.
+    pub fn parse(bytes: &'a [u8]) -> Result<Self, FsmAsmError> {
+        let header = bytes.get(..FSM_HEADER_LEN).ok_or(FsmAsmError::Truncated)?;
+        let bytes = bytes
+            .get(..header[2] as usize)
+            .ok_or(FsmAsmError::Truncated)?;
+
+        let config_a = header[0];
+        if config_a & 0x0C != 0
+            || (config_a & 0x03) as usize > FSM_MAX_TIMERS
+            || header[1] & !CONFIG_B_SUPPORTED != 0
+        {
+            return Err(FsmAsmError::Unsupported);
+        }
+        let counts = [
+            (config_a >> 6) as usize,
+            ((config_a >> 4) & 0x03) as usize,
+            (config_a & 0x03) as usize,
+        ];
+
+        let mut listing = Self {
+            config_b: header[1],
+            settings: FsmSettings::from_bits(header[3]),
+            thresholds: [0.0; FSM_MAX_ITEMS],
+            masks: [FsmMask::default(); FSM_MAX_ITEMS],
+            timers: [0; FSM_MAX_TIMERS],
+            counts,
+            reset_offset: 0,
+            code: &[],
+        };
+
+        let mut pos = FSM_HEADER_LEN;
+        let mut take = |len: usize| {
+            let data = bytes.get(pos..pos + len).ok_or(FsmAsmError::Truncated);
+            pos += len;
+            data
+        };
+        for ths in &mut listing.thresholds[..counts[0]] {
+            let data = take(2)?;
+            *ths = from_f16_to_f32(u16::from_le_bytes([data[0], data[1]]));
+        }
+        for mask in &mut listing.masks[..counts[1]] {
+            let data = take(2)?;
+            *mask = FsmMask {
+                mask: data[0],
+                temporary: data[1],
+            };
+        }
+        if counts[2] != 0 {
+            take(1)?;
+        }
+        for timer in &mut listing.timers[..counts[2]] {
+            let data = take(2)?;
+            *timer = u16::from_le_bytes([data[0], data[1]]);
+        }
+
+        let rp = header[4] as usize;
+        if rp < pos || rp > bytes.len() {
+            return Err(FsmAsmError::InvalidResetPoint);
+        }
+        listing.reset_offset = rp - pos;
+        listing.code = &bytes[pos..];
+
+        Ok(listing)
+    }
+
+    /// Returns the thresholds.
+    pub fn thresholds(&self) -> &[f32] {
+        &self.thresholds[..self.counts[0]]
+    }
+
+    /// Returns the masks.
+    pub fn masks(&self) -> &[FsmMask] {
+        &self.masks[..self.counts[1]]
+    }
+
+    /// Returns the timers.
+    pub fn timers(&self) -> &[u16] {
+        &self.timers[..self.counts[2]]
+    }
+
+    /// Returns an iterator over the instructions with their offset in the code.
+    pub fn instructions(&self) -> FsmInstructions<'a> {
+        FsmInstructions {
+            code: self.code,
+            pos: 0,
+        }
+    }
+
+    /// Decodes the instructions into `instructions` and returns the equivalent source.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<FsmSource, FsmAsmError>`: The source, which assembles back into the same
+    ///   program, or an error if an instruction is truncated, `instructions` is too small or
+    ///   the reset point is not the start of an instruction.
+    pub fn source<'b>(
+        &'b self,
+        instructions: &'b mut [FsmInstruction],
+    ) -> Result<FsmSource<'b>, FsmAsmError> {
+        let mut count = 0;
+        let mut reset_point = None;
+        for item in self.instructions() {
+            let (offset, instruction) = item?;
+            if offset == self.reset_offset {
+                reset_point = Some(count);
+            }
+            *instructions
+                .get_mut(count)
+                .ok_or(FsmAsmError::BufferTooSmall)? = instruction;
+            count += 1;
+        }
+        if self.reset_offset == self.code.len() {
+            reset_point = Some(count);
+        }
+
+        Ok(FsmSource {
+            config_b: self.config_b,
+            settings: self.settings,
+            thresholds: self.thresholds(),
+            masks: self.masks(),
+            timers: self.timers(),
+            reset_point: reset_point.ok_or(FsmAsmError::InvalidResetPoint)?,
+            instructions: &instructions[..count],
+        })
+    }
+}
+
+/// Iterator over the instructions of an [`FsmListing`].
+///
+/// # Description
+///
+/// Yields the offset of each instruction from the start of the code with the instruction,
+/// and stops after the first error.
+#[derive(Clone, Copy)]
+pub struct FsmInstructions<'a> {
+    code: &'a [u8],
+    pos: usize,
+}
+
+impl Iterator for FsmInstructions<'_> {
+    type Item = Result<(usize, FsmInstruction), FsmAsmError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let code = self.code.get(self.pos..).filter(|code| !code.is_empty())?;
+        let offset = self.pos;
+
+        match FsmInstruction::decode(code) {
+            Ok((instruction, len)) => {
+                self.pos += len;
+                Some(Ok((offset, instruction)))
+            }
+            Err(err) => {
+                self.pos = self.code.len();
+                Some(Err(err))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{FOURD, FsmProgram};
+    use super::*;
+
+    const FOURD_CODE: [FsmInstruction; 7] = [
+        FsmInstruction::Condition {
+            reset: FsmCondition::Lrth2,
+            next: FsmCondition::Nzc,
+        },
+        FsmInstruction::Command(FsmCommand::Srp),
+        FsmInstruction::Condition {
+            reset: FsmCondition::Nop,
+            next: FsmCondition::Gnth1,
+        },
+        FsmInstruction::Condition {
+            reset: FsmCondition::Lnth1,
+            next: FsmCondition::Ti3,
+        },
+        FsmInstruction::Command(FsmCommand::Outc),
+        FsmInstruction::Condition {
+            reset: FsmCondition::Nop,
+            next: FsmCondition::Lnth2,
+        },
+        FsmInstruction::Command(FsmCommand::Cont),
+    ];
+
+    #[test]
+    fn half_float_round_trip() {
+        assert_eq!(from_f32_to_f16(0.8), 0x3A66);
+        assert_eq!(from_f32_to_f16(0.2), 0x3266);
+        assert_eq!(from_f32_to_f16(-2.0), 0xC000);
+        assert_eq!(from_f32_to_f16(1.0e6), 0x7C00);
+        assert_eq!(from_f32_to_f16(5.960_464_5e-8), 0x0001);
+        assert_eq!(from_f16_to_f32(from_f32_to_f16(1.5)), 1.5);
+    }
+
+    #[test]
+    fn assemble_fourd() {
+        let source = FsmSource {
+            config_b: 0x10,
+            settings: FsmSettings::default(),
+            thresholds: &[0.8, 0.2],
+            masks: &[FsmMask {
+                mask: 0xF0,
+                temporary: 0x00,
+            }],
+            timers: &[13],
+            reset_point: 0,
+            instructions: &FOURD_CODE,
+        };
+
+        let mut out = [0; 32];
+        let size = source.assemble(&mut out).unwrap();
+        assert_eq!(out[..size], FOURD);
+        assert!(FsmProgram::new(&out[..size]).is_some());
+    }
+
+    #[test]
+    fn disassemble_fourd() {
+        let listing = FsmListing::parse(&FOURD).unwrap();
+        assert_eq!(listing.thresholds().len(), 2);
+        assert_eq!(listing.masks()[0].mask, 0xF0);
+        assert_eq!(listing.timers(), &[13]);
+
+        let mut code = [FsmInstruction::default(); 16];
+        let source = listing.source(&mut code).unwrap();
+        assert_eq!(source.instructions, &FOURD_CODE);
+        assert_eq!(source.reset_point, 0);
+
+        let mut out = [0; 32];
+        let size = source.assemble(&mut out).unwrap();
+        assert_eq!(out[..size], FOURD);
+    }
+
+    #[test]
+    fn errors() {
+        let ambiguous = [FsmInstruction::Condition {
+            reset: FsmCondition::Gnth1,
+            next: FsmCondition::Gnth1,
+        }];
+        let source = FsmSource {
+            config_b: 0,
+            settings: FsmSettings::default(),
+            thresholds: &[],
+            masks: &[],
+            timers: &[],
+            reset_point: 0,
+            instructions: &ambiguous,
+        };
+        assert_eq!(
+            source.assemble(&mut [0; 16]),
+            Err(FsmAsmError::AmbiguousCondition(0x55))
+        );
+
+        let source = FsmSource {
+            reset_point: 2,
+            ..source
+        };
+        assert_eq!(
+            source.assemble(&mut [0; 16]),
+            Err(FsmAsmError::InvalidResetPoint)
+        );
+
+        let config_b = FsmSource {
+            config_b: 0x80,
+            reset_point: 0,
+            ..source
+        };
+        assert_eq!(
+            config_b.assemble(&mut [0; 16]),
+            Err(FsmAsmError::Unsupported)
+        );
+
+        let mut truncated = FOURD;
+        truncated[2] = 4;
+        assert_eq!(FsmListing::parse(&truncated), Err(FsmAsmError::Truncated));
+
+        let mut setp = FOURD;
+        setp[21] = 0x55;
+        let listing = FsmListing::parse(&setp).unwrap();
+        assert_eq!(
+            listing.instructions().last(),
+            Some(Err(FsmAsmError::Truncated))
+        );
+    }
+
+    #[test]
+    fn timers() {
+        let source = |timers, instruction| {
+            let source = FsmSource {
+                config_b: 0,
+                settings: FsmSettings::default(),
+                thresholds: &[],
+                masks: &[],
+                timers,
+                reset_point: 0,
+                instructions: &[instruction],
+            };
+            source.assemble(&mut [0; 32]).map(|_| ())
+        };
+        let condition = |next| FsmInstruction::Condition {
+            reset: FsmCondition::Nop,
+            next,
+        };
+        let jmp = |conditions| {
+            FsmInstruction::Command(FsmCommand::Jmp {
+                conditions,
+                true_offset: 0,
+                false_offset: 0,
+            })
+        };
+
+        assert_eq!(source(&[10], condition(FsmCondition::Ti3)), Ok(()));
+        assert_eq!(source(&[10, 20], condition(FsmCondition::Ti4)), Ok(()));
+        assert_eq!(
+            source(&[], condition(FsmCondition::Ti3)),
+            Err(FsmAsmError::UndeclaredTimer)
+        );
+        assert_eq!(
+            source(&[10], condition(FsmCondition::Ti4)),
+            Err(FsmAsmError::UndeclaredTimer)
+        );
+        assert_eq!(
+            source(&[10], FsmInstruction::Command(FsmCommand::Stimer4(5))),
+            Err(FsmAsmError::UndeclaredTimer)
+        );
+        assert_eq!(source(&[10], jmp(0x53)), Ok(()));
+        assert_eq!(source(&[10], jmp(0x45)), Err(FsmAsmError::UndeclaredTimer));
+
+        // timers 1 and 2 are long timers
+        assert_eq!(
+            source(&[10], condition(FsmCondition::Ti1)),
+            Err(FsmAsmError::Unsupported)
+        );
+        assert_eq!(source(&[10], jmp(0x25)), Err(FsmAsmError::Unsupported));
+        assert_eq!(
+            source(&[10, 20, 30], condition(FsmCondition::Nop)),
+            Err(FsmAsmError::TooManyItems)
+        );
+
+        let mut three_timers = FOURD;
+        three_timers[0] |= 0x03;
+        assert_eq!(
+            FsmListing::parse(&three_timers),
+            Err(FsmAsmError::Unsupported)
+        );
+        let mut config_b = FOURD;
+        config_b[1] |= 0x20;
+        assert_eq!(FsmListing::parse(&config_b), Err(FsmAsmError::Unsupported));
+    }
+}
@@ -0,0 +1,369 @@
+//! Finite state machine programs.
+//!
+//! FSM programs live in the advanced pages of the embedded functions, from
+//! [`FSM_START_ADDRESS`] to [`FSM_MEMORY_END`], one after the other. The device finds them
+//! through `FSM_START_ADD` and `FSM_PROGRAMS`, and runs the programs whose bit is set in
+//! `FSM_ENABLE`: bit `n` enables the `n`-th program in memory.
+//!
+//! [`FsmLayout`] computes where a set of [`FsmProgram`] goes, and `fsm_load_programs` writes
+//! them and returns the layout, whose [`FsmHandle`] identify each program.
+//!
+//! [`FsmOutput`] decodes the `FSM_OUTS` register of a program, and [`FsmEvents`] pairs the
+//! programs flagged in `FSM_STATUS` with their output.
+//!
+//! The [`asm`] module describes programs with typed values, assembles them into the bytes
+//! taken by [`FsmProgram`] and disassembles programs read back with `ln_pg_read`.
+
+pub mod asm;
+
+use super::register::embedded::{FsmEnable, FsmOuts};
+use super::register::main::FsmStatusMainpage;
+
+/// First address of the FSM program memory.
+pub const FSM_START_ADDRESS: u16 = 0x19C;
+
+/// End of the FSM program memory, exclusive.
+pub const FSM_MEMORY_END: u16 = 0x400;
+
+/// Maximum number of FSM programs.
+pub const FSM_MAX_PROGRAMS: usize = 8;
+
+/// Size of the fixed part of an FSM program: `CONFIG_A`, `CONFIG_B`, `SIZE`, `SETTINGS`, the
+/// reset pointer and the program pointer.
+pub const FSM_HEADER_LEN: usize = 6;
+
+/// 4D orientation program from `bsp/src/config/iis2dulpx_fourd_orientation_detection.json`.
+#[cfg(test)]
+pub(crate) const FOURD: [u8; 22] = [
+    0x91, 0x10, 0x16, 0x00, 0x0F, 0x00, 0x66, 0x3A, 0x66, 0x32, 0xF0, 0x00, 0x00, 0x0D, 0x00, 0xEF,
+    0x33, 0x05, 0x73, 0x99, 0x08, 0x22,
+];
+
+/// An FSM program in the byte layout of the program memory.
+///
+/// # Description
+///
+/// The bytes are borrowed, e.g. from a constant exported by ST tools. Programs are enabled when
+/// loaded unless built with [`FsmProgram::with_enabled`].
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct FsmProgram<'a> {
+    bytes: &'a [u8],
+    enabled: bool,
+}
+
+impl<'a> FsmProgram<'a> {
+    /// Wraps the bytes of a program.
+    ///
+    /// # Returns
+    ///
+    /// * `Option<Self>`: `None` if `bytes` is shorter than the fixed part or if its length
+    ///   does not match the `SIZE` byte.
+    pub const fn new(bytes: &'a [u8]) -> Option<Self> {
+        if bytes.len() < FSM_HEADER_LEN || bytes[2] as usize != bytes.len() {
+            return None;
+        }
+
+        Some(Self {
+            bytes,
+            enabled: true,
+        })
+    }
+
+    /// Sets whether the program is enabled once loaded.
+    pub const fn with_enabled(self, enabled: bool) -> Self {
+        Self { enabled, ..self }
+    }
+
+    /// Returns the bytes of the program.
+    pub const fn bytes(&self) -> &'a [u8] {
+        self.bytes
+    }
+
+    /// Returns the size of the program in bytes.
+    pub const fn size(&self) -> u8 {
+        self.bytes[2]
+    }
+
+    /// Returns `true` if the program is enabled once loaded.
+    pub const fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+}
+
+/// Identifies a program loaded in the FSM memory.
+///
+/// # Fields
+///
+/// - `index: u8`: Position of the program, 0 for FSM1. It selects the program in
+///   `FSM_ENABLE`, `FSM_STATUS` and the `FSM_OUTS` registers.
+/// - `address: u16`: Address of the first byte of the program.
+/// - `len: u8`: Size of the program in bytes.
+#[derive(Clone, Copy, PartialEq, Eq, Default, Debug)]
+pub struct FsmHandle {
+    pub index: u8,
+    pub address: u16,
+    pub len: u8,
+}
+
+impl FsmHandle {
+    /// Returns the bit selecting the program in the per-program registers, e.g. for
+    /// `fsm_int1_route_set`.
+    pub const fn mask(&self) -> u8 {
+        1 << self.index
+    }
+}
+
+/// Represents the reasons why programs do not fit in the FSM memory.
+///
+/// # Variants
+///
+/// - `NoProgram`: No program was given.
+/// - `TooManyPrograms`: More than [`FSM_MAX_PROGRAMS`] programs were given.
+/// - `OutOfMemory(usize)`: The programs need the given number of bytes, more than the FSM
+///   memory holds.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum FsmLayoutError {
+    NoProgram,
+    TooManyPrograms,
+    OutOfMemory(usize),
+}
+
+/// Placement of a set of programs in the FSM memory.
+///
+/// # Description
+///
+/// Programs are laid out contiguously from [`FSM_START_ADDRESS`], in the given order.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct FsmLayout {
+    handles: [FsmHandle; FSM_MAX_PROGRAMS],
+    count: u8,
+    enable: u8,
+}
+
+impl FsmLayout {
+    /// Computes the placement of `programs`.
+    pub fn new(programs: &[FsmProgram<'_>]) -> Result<Self, FsmLayoutError> {
+        if programs.is_empty() {
+            return Err(FsmLayoutError::NoProgram);
+        }
+        if programs.len() > FSM_MAX_PROGRAMS {
+            return Err(FsmLayoutError::TooManyPrograms);
+        }
+
+        let required = programs.iter().map(|program| program.size() as usize).sum();
+        if required > (FSM_MEMORY_END - FSM_START_ADDRESS) as usize {
+            return Err(FsmLayoutError::OutOfMemory(required));
+        }
+
+        let mut layout = Self {
+            handles: [FsmHandle::default(); FSM_MAX_PROGRAMS],
+            count: programs.len() as u8,
+            enable: 0,
+        };
+        let mut address = FSM_START_ADDRESS;
+        for (index, (program, handle)) in programs.iter().zip(&mut layout.handles).enumerate() {
+            *handle = FsmHandle {
+                index: index as u8,
+                address,
+                len: program.size(),
+            };
+            address += program.size() as u16;
+            if program.is_enabled() {
+                layout.enable |= 1 << index;
+            }
+        }
+
+        Ok(layout)
+    }
+
+    /// Returns the handles of the programs, in memory order.
+    pub fn handles(&self) -> &[FsmHandle] {
+        &self.handles[..self.count as usize]
+    }
+
+    /// Returns the number of programs.
+    pub fn count(&self) -> u8 {
+        self.count
+    }
+
+    /// Returns the number of bytes used in the FSM memory.
+    pub fn used(&self) -> u16 {
+        self.handles().iter().map(|handle| handle.len as u16).sum()
+    }
+
+    /// Returns the `FSM_ENABLE` value enabling the requested programs.
+    pub fn enable(&self) -> FsmEnable {
+        FsmEnable::from_bits(self.enable)
+    }
+}
+
+/// Output of an FSM program, as set by its `OUTC` commands.
+///
+/// # Fields
+///
+/// - `p_x: u8`: Positive event detected on the X-axis.
+/// - `n_x: u8`: Negative event detected on the X-axis.
+/// - `p_y: u8`: Positive event detected on the Y-axis.
+/// - `n_y: u8`: Negative event detected on the Y-axis.
+/// - `p_z: u8`: Positive event detected on the Z-axis.
+/// - `n_z: u8`: Negative event detected on the Z-axis.
+/// - `p_v: u8`: Positive event detected on the vector.
+/// - `n_v: u8`: Negative event detected on the vector.
+///
+/// # Description
+///
+/// Each field is 1 when the event is detected, 0 otherwise. Converts from the raw `FSM_OUTS`
+/// value.
+#[derive(Clone, Copy, PartialEq, Eq, Default, Debug)]
+pub struct FsmOutput {
+    pub p_x: u8,
+    pub n_x: u8,
+    pub p_y: u8,
+    pub n_y: u8,
+    pub p_z: u8,
+    pub n_z: u8,
+    pub p_v: u8,
+    pub n_v: u8,
+}
+
+impl From<FsmOuts> for FsmOutput {
+    fn from(outs: FsmOuts) -> Self {
+        Self {
+            p_x: outs.p_x(),
+            n_x: outs.n_x(),
+            p_y: outs.p_y(),
+            n_y: outs.n_y(),
+            p_z: outs.p_z(),
+            n_z: outs.n_z(),
+            p_v: outs.p_v(),
+            n_v: outs.n_v(),
+        }
+    }
+}
+
+impl From<u8> for FsmOutput {
+    fn from(value: u8) -> Self {
+        FsmOuts::from_bits(value).into()
+    }
+}
+
+/// Iterator over the FSM programs that fired, with their output.
+///
+/// # Description
+///
+/// Yields `(index, output)` in program order, where `index` is 0 for FSM1 as in
+/// [`FsmHandle::index`], for every program whose bit is set in `FSM_STATUS`. Returned by
+/// `fsm_events_get`.
+#[derive(Clone, Debug)]
+pub struct FsmEvents {
+    status: u8,
+    outs: [u8; FSM_MAX_PROGRAMS],
+    index: u8,
+}
+
+impl FsmEvents {
+    /// Pairs a status read from `FSM_STATUS` with the outputs read from `FSM_OUTS1` to
+    /// `FSM_OUTS8`.
+    pub fn new(status: FsmStatusMainpage, outs: [u8; FSM_MAX_PROGRAMS]) -> Self {
+        Self {
+            status: status.into_bits(),
+            outs,
+            index: 0,
+        }
+    }
+
+    /// Returns `true` if no program fired.
+    pub fn is_empty(&self) -> bool {
+        self.status.checked_shr(self.index as u32).unwrap_or(0) == 0
+    }
+}
+
+impl Iterator for FsmEvents {
+    type Item = (u8, FsmOutput);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while (self.index as usize) < FSM_MAX_PROGRAMS {
+            let index = self.index;
+            self.index += 1;
+            if self.status & (1 << index) != 0 {
+                return Some((index, self.outs[index as usize].into()));
+            }
+        }
+
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn program_size() {
+        assert!(FsmProgram::new(&FOURD).is_some());
+        assert!(FsmProgram::new(&FOURD[..21]).is_none());
+        assert!(FsmProgram::new(&FOURD[..4]).is_none());
+    }
+
+    #[test]
+    fn layout() {
+        let program = FsmProgram::new(&FOURD).unwrap();
+        let layout = FsmLayout::new(&[program, program.with_enabled(false), program]).unwrap();
+
+        assert_eq!(layout.count(), 3);
+        assert_eq!(layout.used(), 66);
+        assert_eq!(layout.handles()[1].address, FSM_START_ADDRESS + 22);
+        assert_eq!(layout.handles()[2].index, 2);
+        assert_eq!(layout.enable().into_bits(), 0b101);
+    }
+
+    #[test]
+    fn layout_errors() {
+        let program = FsmProgram::new(&FOURD).unwrap();
+        assert_eq!(FsmLayout::new(&[]), Err(FsmLayoutError::NoProgram));
+        assert_eq!(
+            FsmLayout::new(&[program; 9]),
+            Err(FsmLayoutError::TooManyPrograms)
+        );
+
+        let mut big = [0u8; 255];
+        big[2] = 255;
+        let big = FsmProgram::new(&big).unwrap();
+        assert_eq!(
+            FsmLayout::new(&[big, big, big]),
+            Err(FsmLayoutError::OutOfMemory(765))
+        );
+    }
+
+    #[test]
+    fn output() {
+        // FOURD sets N_Y when the Y-axis points down.
+        let output = FsmOutput::from(0x10);
+        assert_eq!(
+            output,
+            FsmOutput {
+                n_y: 1,
+                ..Default::default()
+            }
+        );
+        assert_eq!(FsmOutput::from(0x80).p_x, 1);
+    }
+
+    #[test]
+    fn events() {
+        let status = FsmStatusMainpage::from_bits(0b1000_0101);
+        let outs = [0x10, 0, 0x80, 0, 0, 0, 0, 0x01];
+        let events = FsmEvents::new(status, outs);
+        assert!(!events.is_empty());
+
+        let mut events = events;
+        assert_eq!(events.next(), Some((0, FsmOutput::from(0x10))));
+        assert_eq!(events.next(), Some((2, FsmOutput::from(0x80))));
+        assert!(!events.is_empty());
+        assert_eq!(events.next(), Some((7, FsmOutput::from(0x01))));
+        assert!(events.is_empty());
+        assert_eq!(events.next(), None);
+
+        assert!(FsmEvents::new(FsmStatusMainpage::new(), outs).is_empty());
+    }
+}
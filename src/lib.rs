@@ -2,6 +2,9 @@
 #![doc = include_str!("../README.md")]
 #![allow(clippy::duplicate_mod)]
 
+#[cfg(feature = "std")]
+extern crate std;
+
 #[cfg(feature = "async")]
 #[path = "."]
 pub mod asynchronous {
@@ -11,9 +14,19 @@ pub mod asynchronous {
     use embedded_hal_async::spi::SpiDevice;
     use st_mems_bus::asynchronous::*;
 
+    pub mod capture;
     pub mod driver;
+    pub mod fifo;
+    pub mod filter;
+    pub mod fsm;
     pub mod prelude;
+    #[cfg(feature = "heapless")]
+    pub mod queue;
+    pub mod qvar;
+    pub mod reg_config;
     pub mod register;
+    pub mod temperature;
+    pub mod timestamp;
 
     pub use driver::*;
 }
@@ -27,9 +40,19 @@ pub mod blocking {
     use embedded_hal::spi::SpiDevice;
     use st_mems_bus::blocking::*;
 
+    pub mod capture;
     pub mod driver;
+    pub mod fifo;
+    pub mod filter;
+    pub mod fsm;
     pub mod prelude;
+    #[cfg(feature = "heapless")]
+    pub mod queue;
+    pub mod qvar;
+    pub mod reg_config;
     pub mod register;
+    pub mod temperature;
+    pub mod timestamp;
 
     pub use driver::*;
 }
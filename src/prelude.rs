@@ -1,5 +1,6 @@
 use super::register;
 
+pub use super::fifo::*;
 pub use register::advanced::*;
 pub use register::embedded::*;
 pub use register::main::*;
@@ -0,0 +1,190 @@
+//! FIFO producer for `heapless` single-producer single-consumer queues.
+//!
+//! A common firmware layout drains the sensor FIFO from the interrupt handler and processes
+//! the samples in a task. [`FifoProducer`] wraps the producer half of a
+//! [`heapless::spsc::Queue`]: the interrupt handler passes it to `fifo_drain_to_queue`, which
+//! decodes every FIFO record into a [`TimedSample`] and enqueues it, while the task dequeues
+//! samples from the consumer half. Both halves are lock-free, so no critical section is needed
+//! around the queue.
+//!
+//! Samples that do not fit in the queue are counted instead of blocking the producer, as are
+//! FIFO overruns reported by the sensor, so that the consumer can detect gaps.
+//!
+//! This module is available with the `heapless` feature.
+
+use heapless::spsc::Producer;
+
+use super::fifo::{FifoDecoder, FifoSample, TimedSample};
+use super::register::main::FifoRecord;
+
+/// Represents the loss counters of a [`FifoProducer`].
+///
+/// # Fields
+///
+/// - `enqueued: u32`: Number of samples pushed into the queue.
+/// - `queue_full: u32`: Number of decoded samples dropped because the queue was full.
+/// - `fifo_overruns: u32`: Number of drains that found the sensor FIFO in overrun, each one
+///   meaning that an unknown number of samples has been lost in the sensor.
+///
+/// # Description
+///
+/// The counters wrap around on overflow.
+#[derive(Clone, Copy, PartialEq, Default, Debug)]
+pub struct QueueStats {
+    pub enqueued: u32,
+    pub queue_full: u32,
+    pub fifo_overruns: u32,
+}
+
+/// Decodes FIFO records into the producer half of a `heapless` queue.
+///
+/// # Description
+///
+/// The decoder state (configuration changes, timestamps) lives in the producer, so all records
+/// must go through the same instance. The queue holds `N - 1` samples.
+pub struct FifoProducer<'a, const N: usize> {
+    producer: Producer<'a, TimedSample, N>,
+    decoder: FifoDecoder,
+    stats: QueueStats,
+}
+
+impl<'a, const N: usize> FifoProducer<'a, N> {
+    /// Creates a producer decoding records with `decoder`.
+    pub fn new(producer: Producer<'a, TimedSample, N>, decoder: FifoDecoder) -> Self {
+        Self {
+            producer,
+            decoder,
+            stats: QueueStats::default(),
+        }
+    }
+
+    /// Decodes a record and enqueues it.
+    ///
+    /// # Returns
+    ///
+    /// * `bool`: `false` if the queue was full and the sample has been dropped. Empty records
+    ///   are not enqueued and return `true`.
+    pub fn push(&mut self, record: &FifoRecord) -> bool {
+        let timed = self.decoder.decode_timed(record);
+        if timed.sample == FifoSample::Empty {
+            return true;
+        }
+
+        match self.producer.enqueue(timed) {
+            Ok(()) => {
+                self.stats.enqueued = self.stats.enqueued.wrapping_add(1);
+                true
+            }
+            Err(_) => {
+                self.stats.queue_full = self.stats.queue_full.wrapping_add(1);
+                false
+            }
+        }
+    }
+
+    /// Records that the sensor FIFO has overrun.
+    pub fn note_fifo_overrun(&mut self) {
+        self.stats.fifo_overruns = self.stats.fifo_overruns.wrapping_add(1);
+    }
+
+    /// Returns the number of free slots in the queue.
+    pub fn free(&self) -> usize {
+        self.producer.capacity() - self.producer.len()
+    }
+
+    /// Returns the loss counters.
+    pub fn stats(&self) -> QueueStats {
+        self.stats
+    }
+
+    /// Returns the loss counters and clears them.
+    pub fn take_stats(&mut self) -> QueueStats {
+        core::mem::take(&mut self.stats)
+    }
+
+    /// Returns the decoder, e.g. to read the current sensor mode.
+    pub fn decoder(&self) -> &FifoDecoder {
+        &self.decoder
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::register::main::{Batch, FifoMode, FifoSensorTag, Md};
+    use super::*;
+    use heapless::spsc::Queue;
+
+    const XL_RECORD: FifoRecord = FifoRecord {
+        tag: FifoSensorTag::XlTempTag as u8,
+        data: [0x00, 0x40, 0, 0, 0, 0],
+    };
+
+    const EMPTY_RECORD: FifoRecord = FifoRecord {
+        tag: FifoSensorTag::FifoEmpty as u8,
+        data: [0; 6],
+    };
+
+    fn decoder() -> FifoDecoder {
+        let f_md = FifoMode {
+            xl_only: 1,
+            ..Default::default()
+        };
+        FifoDecoder::new(&Md::default(), &f_md, &Batch::default())
+    }
+
+    #[test]
+    fn push_decoded() {
+        let mut queue = Queue::<TimedSample, 4>::new();
+        let (producer, mut consumer) = queue.split();
+        let mut producer = FifoProducer::new(producer, decoder());
+        assert_eq!(producer.free(), 3);
+
+        assert!(producer.push(&XL_RECORD));
+        assert_eq!(producer.free(), 2);
+
+        let timed = consumer.dequeue().unwrap();
+        let FifoSample::Accel(xl) = timed.sample else {
+            panic!("unexpected sample");
+        };
+        assert_eq!(xl.raw, [0x4000, 0, 0]);
+        assert_eq!(producer.stats().enqueued, 1);
+    }
+
+    #[test]
+    fn skip_empty() {
+        let mut queue = Queue::<TimedSample, 4>::new();
+        let (producer, mut consumer) = queue.split();
+        let mut producer = FifoProducer::new(producer, decoder());
+
+        assert!(producer.push(&EMPTY_RECORD));
+        assert!(consumer.dequeue().is_none());
+        assert_eq!(producer.stats(), QueueStats::default());
+    }
+
+    #[test]
+    fn queue_full_and_take_stats() {
+        let mut queue = Queue::<TimedSample, 3>::new();
+        let (producer, mut consumer) = queue.split();
+        let mut producer = FifoProducer::new(producer, decoder());
+
+        // the consumer lags: only two samples fit
+        assert!(producer.push(&XL_RECORD));
+        assert!(producer.push(&XL_RECORD));
+        assert!(!producer.push(&XL_RECORD));
+        assert!(!producer.push(&XL_RECORD));
+        producer.note_fifo_overrun();
+
+        let expected = QueueStats {
+            enqueued: 2,
+            queue_full: 2,
+            fifo_overruns: 1,
+        };
+        assert_eq!(producer.stats(), expected);
+        assert_eq!(producer.take_stats(), expected);
+        assert_eq!(producer.stats(), QueueStats::default());
+
+        consumer.dequeue();
+        assert!(producer.push(&XL_RECORD));
+        assert_eq!(producer.stats().enqueued, 1);
+    }
+}
@@ -0,0 +1,341 @@
+//! AH_QVAR touch and presence detection.
+//!
+//! The AH_QVAR channel measures the electrostatic charge variation on an external electrode.
+//! [`QvarDetector`] turns the stream of millivolt readings coming from `ah_qvar_data_get` or
+//! from `XlAndQvar` FIFO entries into [`QvarEvent`]s:
+//!
+//! * a slowly adapting baseline absorbs drift while the electrode is not touched;
+//! * the deviation from the baseline is compared against touch and release thresholds
+//!   (hysteresis);
+//! * a state change is confirmed only after a number of consecutive samples (debouncing);
+//! * touch duration and the gap between touches are tracked to report long presses and
+//!   double touches.
+//!
+//! Time parameters are expressed in milliseconds and converted to samples using the active
+//! [`Odr`].
+
+use super::fifo::FifoSample;
+use super::register::main::{AhQvarData, Odr};
+
+/// Represents the events reported by [`QvarDetector`].
+///
+/// # Variants
+///
+/// - `TouchDown`: A touch has been confirmed.
+/// - `TouchUp`: The touch has been released.
+/// - `LongPress`: The touch has been held for the configured long-press time. Reported once
+///   per touch.
+/// - `DoubleTouch`: A touch has been confirmed within the double-touch gap after the previous
+///   release. Reported instead of `TouchDown`.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum QvarEvent {
+    TouchDown,
+    TouchUp,
+    LongPress,
+    DoubleTouch,
+}
+
+/// Represents the touch detection parameters.
+///
+/// # Fields
+///
+/// - `touch_threshold_mv: f32`: Deviation from the baseline, in absolute value, above which
+///   the electrode is considered touched.
+/// - `release_threshold_mv: f32`: Deviation below which the touch is considered released.
+///   Must not exceed `touch_threshold_mv`.
+/// - `debounce_ms: u16`: Time the deviation must stay beyond a threshold to confirm a state
+///   change.
+/// - `long_press_ms: u16`: Touch duration reported as `LongPress`, 0 to disable.
+/// - `double_touch_ms: u16`: Maximum gap between a release and the next touch reported as
+///   `DoubleTouch`, 0 to disable.
+/// - `baseline_alpha: f32`: Weight of each new untouched sample in the baseline average,
+///   in the `(0, 1]` range.
+///
+/// # Description
+///
+/// Thresholds depend on the electrode, the `AhQvarGain` and the `AhQvarZin` settings and
+/// should be tuned on the final hardware.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct QvarConfig {
+    pub touch_threshold_mv: f32,
+    pub release_threshold_mv: f32,
+    pub debounce_ms: u16,
+    pub long_press_ms: u16,
+    pub double_touch_ms: u16,
+    pub baseline_alpha: f32,
+}
+
+impl Default for QvarConfig {
+    fn default() -> Self {
+        Self {
+            touch_threshold_mv: 20.0,
+            release_threshold_mv: 10.0,
+            debounce_ms: 40,
+            long_press_ms: 1000,
+            double_touch_ms: 300,
+            baseline_alpha: 0.01,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum TouchState {
+    Released,
+    Touched,
+}
+
+/// Turns AH_QVAR readings into touch events.
+///
+/// # Description
+///
+/// Feed every sample in acquisition order through [`QvarDetector::process_mv`] or one of
+/// its wrappers. At most one event is returned per sample.
+#[derive(Clone, Copy, Debug)]
+pub struct QvarDetector {
+    config: QvarConfig,
+    debounce: u32,
+    long_press: u32,
+    double_touch: u32,
+    baseline: Option<f32>,
+    state: TouchState,
+    pending: u32,
+    touch_len: u32,
+    since_release: Option<u32>,
+}
+
+impl QvarDetector {
+    /// Creates a detector for samples produced at the sampling frequency of `odr`.
+    ///
+    /// # Returns
+    ///
+    /// * `Option<Self>`: `None` if `odr` has no fixed rate, the thresholds are inconsistent
+    ///   or `baseline_alpha` is out of range.
+    pub fn new(config: QvarConfig, odr: Odr) -> Option<Self> {
+        let hz = odr.hz()?;
+
+        if config.release_threshold_mv > config.touch_threshold_mv
+            || config.release_threshold_mv < 0.0
+            || config.baseline_alpha <= 0.0
+            || config.baseline_alpha > 1.0
+        {
+            return None;
+        }
+
+        let samples = |ms: u16| (ms as f32 * hz / 1000.0) as u32;
+
+        Some(Self {
+            config,
+            debounce: samples(config.debounce_ms).max(1),
+            long_press: samples(config.long_press_ms),
+            double_touch: samples(config.double_touch_ms),
+            baseline: None,
+            state: TouchState::Released,
+            pending: 0,
+            touch_len: 0,
+            since_release: None,
+        })
+    }
+
+    /// Processes one reading in millivolts.
+    pub fn process_mv(&mut self, mv: f32) -> Option<QvarEvent> {
+        let baseline = *self.baseline.get_or_insert(mv);
+        let delta = mv - baseline;
+        let delta = if delta < 0.0 { -delta } else { delta };
+
+        if let Some(gap) = self.since_release.as_mut() {
+            *gap = gap.saturating_add(1);
+        }
+
+        match self.state {
+            TouchState::Released => {
+                if delta >= self.config.touch_threshold_mv {
+                    self.pending += 1;
+                    if self.pending >= self.debounce {
+                        self.pending = 0;
+                        self.state = TouchState::Touched;
+                        self.touch_len = 0;
+
+                        let double = self.double_touch > 0
+                            && self
+                                .since_release
+                                .is_some_and(|gap| gap <= self.double_touch);
+                        self.since_release = None;
+
+                        return Some(if double {
+                            QvarEvent::DoubleTouch
+                        } else {
+                            QvarEvent::TouchDown
+                        });
+                    }
+                } else {
+                    self.pending = 0;
+                    self.baseline = Some(baseline + self.config.baseline_alpha * (mv - baseline));
+                }
+                None
+            }
+            TouchState::Touched => {
+                self.touch_len = self.touch_len.saturating_add(1);
+
+                if delta <= self.config.release_threshold_mv {
+                    self.pending += 1;
+                    if self.pending >= self.debounce {
+                        self.pending = 0;
+                        self.state = TouchState::Released;
+                        self.since_release = Some(0);
+                        return Some(QvarEvent::TouchUp);
+                    }
+                } else {
+                    self.pending = 0;
+                }
+
+                if self.long_press > 0 && self.touch_len == self.long_press {
+                    return Some(QvarEvent::LongPress);
+                }
+                None
+            }
+        }
+    }
+
+    /// Processes a reading returned by `ah_qvar_data_get`.
+    pub fn process(&mut self, data: &AhQvarData) -> Option<QvarEvent> {
+        self.process_mv(data.mv)
+    }
+
+    /// Processes a FIFO record. Only `AccelQvar` samples are used, others return `None`.
+    pub fn process_fifo(&mut self, sample: &FifoSample) -> Option<QvarEvent> {
+        match sample {
+            FifoSample::AccelQvar { ah_qvar, .. } => self.process_mv(ah_qvar.mv),
+            _ => None,
+        }
+    }
+
+    /// Returns `true` while a touch is confirmed.
+    pub fn is_touched(&self) -> bool {
+        self.state == TouchState::Touched
+    }
+
+    /// Returns the current baseline in millivolts, if any sample has been processed.
+    pub fn baseline(&self) -> Option<f32> {
+        self.baseline
+    }
+
+    /// Clears the baseline and the touch state.
+    pub fn reset(&mut self) {
+        self.baseline = None;
+        self.state = TouchState::Released;
+        self.pending = 0;
+        self.touch_len = 0;
+        self.since_release = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Debounce of 2 samples, long press after 10 samples and double touch within 10 samples
+    /// at 100 Hz.
+    fn detector() -> QvarDetector {
+        let config = QvarConfig {
+            debounce_ms: 20,
+            long_press_ms: 100,
+            double_touch_ms: 100,
+            ..Default::default()
+        };
+
+        QvarDetector::new(config, Odr::_100hzLp).unwrap()
+    }
+
+    /// Feeds `count` readings of `mv` and returns the events, in order.
+    fn feed(detector: &mut QvarDetector, mv: f32, count: usize) -> [Option<QvarEvent>; 2] {
+        let mut events = [None; 2];
+        let mut found = 0;
+        for _ in 0..count {
+            if let Some(event) = detector.process_mv(mv) {
+                events[found] = Some(event);
+                found += 1;
+            }
+        }
+
+        events
+    }
+
+    #[test]
+    fn config() {
+        assert!(QvarDetector::new(QvarConfig::default(), Odr::_100hzLp).is_some());
+        assert!(QvarDetector::new(QvarConfig::default(), Odr::Off).is_none());
+
+        let inverted = QvarConfig {
+            release_threshold_mv: 30.0,
+            ..Default::default()
+        };
+        assert!(QvarDetector::new(inverted, Odr::_100hzLp).is_none());
+
+        let frozen = QvarConfig {
+            baseline_alpha: 0.0,
+            ..Default::default()
+        };
+        assert!(QvarDetector::new(frozen, Odr::_100hzLp).is_none());
+    }
+
+    #[test]
+    fn thresholds_and_debounce() {
+        let mut detector = detector();
+        assert_eq!(detector.process_mv(100.0), None);
+        assert_eq!(detector.baseline(), Some(100.0));
+
+        // Below the touch threshold, or not long enough.
+        assert_eq!(feed(&mut detector, 119.0, 5), [None; 2]);
+        assert_eq!(detector.process_mv(125.0), None);
+        assert_eq!(detector.process_mv(100.0), None);
+        assert!(!detector.is_touched());
+
+        // Negative deviations count as well.
+        assert_eq!(detector.process_mv(75.0), None);
+        assert_eq!(detector.process_mv(75.0), Some(QvarEvent::TouchDown));
+        assert!(detector.is_touched());
+
+        // Released only below the release threshold.
+        assert_eq!(feed(&mut detector, 88.0, 5), [None; 2]);
+        assert_eq!(detector.process_mv(95.0), None);
+        assert_eq!(detector.process_mv(95.0), Some(QvarEvent::TouchUp));
+        assert!(!detector.is_touched());
+
+        detector.reset();
+        assert_eq!(detector.baseline(), None);
+    }
+
+    #[test]
+    fn long_press_and_double_touch() {
+        let mut detector = detector();
+        detector.process_mv(0.0);
+
+        assert_eq!(
+            feed(&mut detector, 25.0, 2),
+            [Some(QvarEvent::TouchDown), None]
+        );
+        assert_eq!(feed(&mut detector, 25.0, 9), [None; 2]);
+        assert_eq!(detector.process_mv(25.0), Some(QvarEvent::LongPress));
+        assert_eq!(feed(&mut detector, 25.0, 20), [None; 2]);
+        assert_eq!(
+            feed(&mut detector, 0.0, 2),
+            [Some(QvarEvent::TouchUp), None]
+        );
+
+        assert_eq!(feed(&mut detector, 0.0, 3), [None; 2]);
+        assert_eq!(
+            feed(&mut detector, 25.0, 2),
+            [Some(QvarEvent::DoubleTouch), None]
+        );
+        assert_eq!(
+            feed(&mut detector, 0.0, 2),
+            [Some(QvarEvent::TouchUp), None]
+        );
+
+        assert_eq!(feed(&mut detector, 0.0, 20), [None; 2]);
+        assert_eq!(
+            feed(&mut detector, 25.0, 2),
+            [Some(QvarEvent::TouchDown), None]
+        );
+    }
+}
@@ -0,0 +1,347 @@
+//! Binary container for register configuration programs.
+//!
+//! The container stores a program in two bytes per entry behind a fixed 16-byte header, so it
+//! can be stored in flash or sent to a device much more cheaply than the JSON export. All
+//! multi-byte fields are little-endian:
+//!
+//! | Offset | Size | Field                                                  |
+//! |--------|------|--------------------------------------------------------|
+//! | 0      | 4    | Magic, `RCFG`                                          |
+//! | 4      | 1    | Format version, [`VERSION`]                            |
+//! | 5      | 1    | Target device, the `WHO_AM_I` value of the sensor      |
+//! | 6      | 1    | Program type, see [`ProgramType`]                      |
+//! | 7      | 1    | Reserved, 0                                            |
+//! | 8      | 2    | Number of entries                                      |
+//! | 10     | 2    | Reserved, 0                                            |
+//! | 12     | 4    | CRC-32 (IEEE) of bytes 0..12 followed by the entries   |
+//! | 16     | 2 * n | Entries                                               |
+//!
+//! An entry is an `(address, data)` byte pair. Register addresses are 7 bits wide, so the
+//! address [`DELAY_ADDRESS`] encodes a delay of `data` milliseconds.
+//!
+//! [`BinProgram::parse`] checks the whole container, CRC included, before any entry is
+//! returned, which lets `load_reg_config_bin` reject a corrupted program without writing to
+//! the device. With the `std` feature, [`from_json`] converts a `reg_config` JSON file.
+
+use derive_more::TryFrom;
+
+use super::super::driver::ID;
+use super::{UcfEntry, UcfOp};
+
+/// Magic bytes at the start of a container.
+pub const MAGIC: [u8; 4] = *b"RCFG";
+
+/// Version of the container format.
+pub const VERSION: u8 = 1;
+
+/// Size of the header in bytes.
+pub const HEADER_LEN: usize = 16;
+
+/// Address encoding a delay entry.
+pub const DELAY_ADDRESS: u8 = 0xFF;
+
+/// Represents the kind of program stored in a container.
+///
+/// # Variants
+///
+/// - `Generic`: Register configuration not tied to an embedded engine.
+/// - `Mlc`: Machine learning core configuration.
+/// - `Fsm`: Finite state machine configuration.
+#[repr(u8)]
+#[derive(Clone, Copy, PartialEq, Eq, Default, Debug, TryFrom)]
+#[try_from(repr)]
+pub enum ProgramType {
+    #[default]
+    Generic = 0x00,
+    Mlc = 0x01,
+    Fsm = 0x02,
+}
+
+/// Represents the errors reported when encoding or parsing a container.
+///
+/// # Variants
+///
+/// - `TooShort`: The container is shorter than its header and entries.
+/// - `BadMagic`: The container does not start with [`MAGIC`].
+/// - `UnsupportedVersion(u8)`: The format version is not [`VERSION`].
+/// - `WrongDevice(u8)`: The program targets another device, identified by its `WHO_AM_I`.
+/// - `UnknownProgramType(u8)`: The program type is not a [`ProgramType`].
+/// - `BadCrc`: The CRC does not match the content.
+/// - `InvalidEntry(usize)`: The entry at the given index has an address outside the register
+///   map.
+/// - `BufferTooSmall`: The output buffer cannot hold the encoded program.
+/// - `TooManyEntries`: The program has more than `u16::MAX` entries.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum BinError {
+    TooShort,
+    BadMagic,
+    UnsupportedVersion(u8),
+    WrongDevice(u8),
+    UnknownProgramType(u8),
+    BadCrc,
+    InvalidEntry(usize),
+    BufferTooSmall,
+    TooManyEntries,
+}
+
+/// Represents the header of a container.
+///
+/// # Fields
+///
+/// - `device: u8`: `WHO_AM_I` value of the target device.
+/// - `program_type: ProgramType`: The kind of program.
+/// - `entries: u16`: Number of entries.
+/// - `crc: u32`: CRC-32 of the container.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct BinHeader {
+    pub device: u8,
+    pub program_type: ProgramType,
+    pub entries: u16,
+    pub crc: u32,
+}
+
+/// Computes the CRC-32 (IEEE 802.3, reflected, polynomial `0x04C11DB7`) of `chunks`.
+fn crc32(chunks: &[&[u8]]) -> u32 {
+    let mut crc = !0u32;
+    for byte in chunks.iter().flat_map(|chunk| chunk.iter()) {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            crc = (crc >> 1) ^ (0xEDB8_8320 & (crc & 1).wrapping_neg());
+        }
+    }
+
+    !crc
+}
+
+/// Returns the size of the container holding `entries` entries.
+pub const fn encoded_len(entries: usize) -> usize {
+    HEADER_LEN + 2 * entries
+}
+
+/// Encodes a program into `out`.
+///
+/// # Returns
+///
+/// * `Result<usize, BinError>`: The number of bytes written, or an error if the program has
+///   too many entries, `out` is too small or an entry writes outside the register map.
+pub fn encode(
+    config: &[UcfEntry],
+    program_type: ProgramType,
+    out: &mut [u8],
+) -> Result<usize, BinError> {
+    let len = encoded_len(config.len());
+    let count = u16::try_from(config.len()).map_err(|_| BinError::TooManyEntries)?;
+    if out.len() < len {
+        return Err(BinError::BufferTooSmall);
+    }
+
+    let (header, body) = out[..len].split_at_mut(HEADER_LEN);
+    for (index, (entry, slot)) in config.iter().zip(body.chunks_exact_mut(2)).enumerate() {
+        let address = match entry.op {
+            UcfOp::Write if entry.address < 0x80 => entry.address,
+            UcfOp::Write => return Err(BinError::InvalidEntry(index)),
+            UcfOp::Delay => DELAY_ADDRESS,
+        };
+        slot.copy_from_slice(&[address, entry.data]);
+    }
+
+    header[..4].copy_from_slice(&MAGIC);
+    header[4..8].copy_from_slice(&[VERSION, ID, program_type as u8, 0]);
+    header[8..10].copy_from_slice(&count.to_le_bytes());
+    header[10..12].fill(0);
+    let crc = crc32(&[&header[..12], body]);
+    header[12..16].copy_from_slice(&crc.to_le_bytes());
+
+    Ok(len)
+}
+
+/// A validated binary container.
+///
+/// # Description
+///
+/// Borrows the container; the entries are decoded on the fly by [`BinProgram::entries`].
+#[derive(Clone, Copy, Debug)]
+pub struct BinProgram<'a> {
+    header: BinHeader,
+    body: &'a [u8],
+}
+
+impl<'a> BinProgram<'a> {
+    /// Parses and validates a container.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Self, BinError>`: The program, or the first check that failed. Bytes after
+    ///   the last entry are ignored.
+    ///
+    /// # Description
+    ///
+    /// Checks, in order, the magic, the version, the target device, the program type, the
+    /// length, the CRC and the entry addresses.
+    pub fn parse(buf: &'a [u8]) -> Result<Self, BinError> {
+        let header = buf.get(..HEADER_LEN).ok_or(BinError::TooShort)?;
+        if header[..4] != MAGIC {
+            return Err(BinError::BadMagic);
+        }
+        if header[4] != VERSION {
+            return Err(BinError::UnsupportedVersion(header[4]));
+        }
+        if header[5] != ID {
+            return Err(BinError::WrongDevice(header[5]));
+        }
+        let program_type = ProgramType::try_from(header[6])
+            .map_err(|_| BinError::UnknownProgramType(header[6]))?;
+
+        let entries = u16::from_le_bytes([header[8], header[9]]);
+        let body = buf
+            .get(HEADER_LEN..encoded_len(entries as usize))
+            .ok_or(BinError::TooShort)?;
+        let crc = u32::from_le_bytes([header[12], header[13], header[14], header[15]]);
+        if crc32(&[&header[..12], body]) != crc {
+            return Err(BinError::BadCrc);
+        }
+        if let Some(index) = body
+            .chunks_exact(2)
+            .position(|entry| entry[0] >= 0x80 && entry[0] != DELAY_ADDRESS)
+        {
+            return Err(BinError::InvalidEntry(index));
+        }
+
+        Ok(Self {
+            header: BinHeader {
+                device: header[5],
+                program_type,
+                entries,
+                crc,
+            },
+            body,
+        })
+    }
+
+    /// Returns the header.
+    pub fn header(&self) -> BinHeader {
+        self.header
+    }
+
+    /// Returns an iterator over the entries, in execution order.
+    pub fn entries(&self) -> impl Iterator<Item = UcfEntry> + 'a {
+        self.body.chunks_exact(2).map(|entry| match entry[0] {
+            DELAY_ADDRESS => UcfEntry::delay(entry[1]),
+            address => UcfEntry::write(address, entry[1]),
+        })
+    }
+}
+
+/// Represents the errors reported by [`from_json`].
+///
+/// # Variants
+///
+/// - `Json(JsonError)`: The JSON file could not be parsed.
+/// - `Bin(BinError)`: The program could not be encoded.
+#[cfg(feature = "std")]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ConvertError {
+    Json(super::json::JsonError),
+    Bin(BinError),
+}
+
+/// Converts a `reg_config` JSON file, such as the ones in `bsp/src/config`, into a container.
+///
+/// Available with the `std` feature.
+#[cfg(feature = "std")]
+pub fn from_json(
+    json: &[u8],
+    program_type: ProgramType,
+) -> Result<std::vec::Vec<u8>, ConvertError> {
+    let program = super::json::JsonProgram::parse(json).map_err(ConvertError::Json)?;
+    let config = program
+        .entries()
+        .collect::<Result<std::vec::Vec<_>, _>>()
+        .map_err(ConvertError::Json)?;
+
+    let mut out = std::vec![0; encoded_len(config.len())];
+    encode(&config, program_type, &mut out).map_err(ConvertError::Bin)?;
+
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PROGRAM: [UcfEntry; 4] = [
+        UcfEntry::write(0x3F, 0x80),
+        UcfEntry::delay(5),
+        UcfEntry::write(0x04, 0x01),
+        UcfEntry::write(0x3F, 0x00),
+    ];
+
+    fn encoded() -> [u8; encoded_len(PROGRAM.len())] {
+        let mut out = [0; encoded_len(PROGRAM.len())];
+        assert_eq!(encode(&PROGRAM, ProgramType::Fsm, &mut out), Ok(out.len()));
+        out
+    }
+
+    #[test]
+    fn crc_check_value() {
+        assert_eq!(crc32(&[b"1234", b"56789"]), 0xCBF4_3926);
+    }
+
+    #[test]
+    fn round_trip() {
+        let out = encoded();
+        assert_eq!(&out[..8], b"RCFG\x01\x47\x02\x00");
+        assert_eq!(&out[16..20], &[0x3F, 0x80, DELAY_ADDRESS, 5]);
+
+        let program = BinProgram::parse(&out).unwrap();
+        assert_eq!(program.header().program_type, ProgramType::Fsm);
+        assert_eq!(program.header().entries, 4);
+        assert!(program.entries().eq(PROGRAM));
+    }
+
+    #[test]
+    fn rejects_corruption() {
+        let out = encoded();
+        assert_eq!(
+            BinProgram::parse(&out[..out.len() - 1]).unwrap_err(),
+            BinError::TooShort
+        );
+
+        let mut corrupted = out;
+        corrupted[17] ^= 0x01;
+        assert_eq!(BinProgram::parse(&corrupted).unwrap_err(), BinError::BadCrc);
+
+        let mut other = out;
+        other[5] = 0x44;
+        assert_eq!(
+            BinProgram::parse(&other).unwrap_err(),
+            BinError::WrongDevice(0x44)
+        );
+
+        let mut small = [0; 10];
+        assert_eq!(
+            encode(&PROGRAM, ProgramType::Fsm, &mut small),
+            Err(BinError::BufferTooSmall)
+        );
+        assert_eq!(
+            encode(
+                &[UcfEntry::write(0x80, 0)],
+                ProgramType::Generic,
+                &mut [0; 18]
+            ),
+            Err(BinError::InvalidEntry(0))
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn bsp_conversion() {
+        let json = include_bytes!("../../bsp/src/config/iis2dulpx_vibration_monitoring.json");
+        let out = from_json(json, ProgramType::Mlc).unwrap();
+        assert_eq!(out.len(), encoded_len(78));
+
+        let program = BinProgram::parse(&out).unwrap();
+        let expected = super::super::json::JsonProgram::parse(json).unwrap();
+        assert!(program.entries().eq(expected.entries().map(Result::unwrap)));
+    }
+}
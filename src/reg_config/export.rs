@@ -0,0 +1,88 @@
+//! Host-side helpers for register configuration programs.
+//!
+//! Used by the `iis2dulpx-config` tool and by build scripts that turn the JSON exports into
+//! Rust constants. This module is available with the `std` feature.
+
+use core::fmt::Write;
+use std::string::String;
+use std::vec::Vec;
+
+use super::binary::{BinProgram, ConvertError, MAGIC};
+use super::json::JsonProgram;
+use super::{UcfEntry, UcfOp};
+
+/// Reads a program from a `reg_config` JSON file or a binary container.
+///
+/// # Returns
+///
+/// * `Result<Vec<UcfEntry>, ConvertError>`: The entries, or the parse error. Containers are
+///   recognized by their magic bytes, anything else is parsed as JSON.
+pub fn read_program(bytes: &[u8]) -> Result<Vec<UcfEntry>, ConvertError> {
+    if bytes.starts_with(&MAGIC) {
+        let program = BinProgram::parse(bytes).map_err(ConvertError::Bin)?;
+        return Ok(program.entries().collect());
+    }
+
+    JsonProgram::parse(bytes)
+        .map_err(ConvertError::Json)?
+        .entries()
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(ConvertError::Json)
+}
+
+/// Renders a program as a Rust constant.
+///
+/// # Arguments
+///
+/// - `name: &str`: Name of the constant.
+/// - `reg_config_path: &str`: Path of the `reg_config` module as seen from the generated
+///   file, e.g. `iis2dulpx_rs::blocking::reg_config`.
+/// - `config: &[UcfEntry]`: The program.
+///
+/// # Returns
+///
+/// * `String`: A Rust source file defining `pub const <name>: [UcfEntry; N]`, ready to be
+///   passed to `load_reg_config`.
+pub fn rust_source(name: &str, reg_config_path: &str, config: &[UcfEntry]) -> String {
+    let mut out = String::new();
+
+    // Writing to a `String` cannot fail.
+    let _ = writeln!(out, "// Generated by iis2dulpx-config, do not edit.");
+    let _ = writeln!(out);
+    let _ = writeln!(out, "use {reg_config_path}::UcfEntry;");
+    let _ = writeln!(out);
+    let _ = writeln!(out, "pub const {name}: [UcfEntry; {}] = [", config.len());
+    for entry in config {
+        let _ = match entry.op {
+            UcfOp::Write => writeln!(
+                out,
+                "    UcfEntry::write(0x{:02X}, 0x{:02X}),",
+                entry.address, entry.data
+            ),
+            UcfOp::Delay => writeln!(out, "    UcfEntry::delay({}),", entry.data),
+        };
+    }
+    let _ = writeln!(out, "];");
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn json_to_rust() {
+        let json =
+            include_bytes!("../../bsp/src/config/iis2dulpx_fourd_orientation_detection.json");
+        let config = read_program(json).unwrap();
+        let source = rust_source("FOURD", "crate::reg_config", &config);
+
+        assert!(source.contains("use crate::reg_config::UcfEntry;\n"));
+        assert!(source.contains(&std::format!(
+            "pub const FOURD: [UcfEntry; {}] = [\n",
+            config.len()
+        )));
+        assert!(source.contains("    UcfEntry::write(0x14, 0x00),\n    UcfEntry::write(0x13, 0x10),\n    UcfEntry::delay(5),\n"));
+    }
+}
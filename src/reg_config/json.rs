@@ -0,0 +1,446 @@
+//! Runtime parser for the `reg_config` JSON format.
+//!
+//! The parser works in place on a byte slice, without allocation, and only understands the
+//! subset of JSON needed by the format:
+//!
+//! ```json
+//! {
+//!   "json_format": { "type": "reg_config", "version": "2.0" },
+//!   "sensors": [
+//!     {
+//!       "name": ["IIS2DULPX"],
+//!       "configuration": [
+//!         { "type": "write", "address": "0x14", "data": "0x00" },
+//!         { "type": "delay", "data": "5" }
+//!       ]
+//!     }
+//!   ]
+//! }
+//! ```
+//!
+//! [`JsonProgram::parse`] checks the format and locates the configuration of the IIS2DULPX
+//! sensor; [`JsonProgram::entries`] then decodes the entries one at a time. Other members
+//! (`description`, `outputs`, `mlc_identifiers`, ...) are skipped.
+
+use super::UcfEntry;
+
+/// Name of the sensor expected in `sensors[].name`.
+pub const SENSOR_NAME: &str = "IIS2DULPX";
+
+/// Maximum nesting of skipped JSON values.
+const MAX_DEPTH: u8 = 16;
+
+/// Represents the errors reported by the JSON parser.
+///
+/// # Variants
+///
+/// - `Syntax(usize)`: Malformed JSON at the given byte offset.
+/// - `TooDeep(usize)`: Values nested deeper than the parser supports, at the given byte offset.
+/// - `Format`: `json_format` is missing or is not `reg_config` version `2.0`.
+/// - `SensorNotFound`: No entry of `sensors` is named IIS2DULPX or has a configuration.
+/// - `UnsupportedOp(usize)`: Configuration entry other than `write` or `delay`, at the given
+///   byte offset.
+/// - `InvalidNumber(usize)`: Address or data that is not a decimal or `0x` hexadecimal value
+///   fitting in a byte, at the given byte offset.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum JsonError {
+    Syntax(usize),
+    TooDeep(usize),
+    Format,
+    SensorNotFound,
+    UnsupportedOp(usize),
+    InvalidNumber(usize),
+}
+
+#[derive(Clone, Copy)]
+struct Cursor<'a> {
+    buf: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Cursor<'a> {
+    fn skip_ws(&mut self) {
+        while let Some(b' ' | b'\t' | b'\r' | b'\n') = self.buf.get(self.pos) {
+            self.pos += 1;
+        }
+    }
+
+    fn peek(&mut self) -> Option<u8> {
+        self.skip_ws();
+        self.buf.get(self.pos).copied()
+    }
+
+    fn expect(&mut self, c: u8) -> Result<(), JsonError> {
+        if self.peek() != Some(c) {
+            return Err(JsonError::Syntax(self.pos));
+        }
+        self.pos += 1;
+        Ok(())
+    }
+
+    /// Consumes `c` if it is the next character.
+    fn eat(&mut self, c: u8) -> bool {
+        let found = self.peek() == Some(c);
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    /// Returns the raw content of a string, escape sequences included.
+    fn string(&mut self) -> Result<&'a [u8], JsonError> {
+        self.expect(b'"')?;
+        let start = self.pos;
+
+        loop {
+            match self.buf.get(self.pos) {
+                Some(b'"') => break,
+                Some(b'\\') => self.pos += 2,
+                Some(_) => self.pos += 1,
+                None => return Err(JsonError::Syntax(start)),
+            }
+        }
+
+        let content = &self.buf[start..self.pos];
+        self.pos += 1;
+        Ok(content)
+    }
+
+    fn skip_value(&mut self, depth: u8) -> Result<(), JsonError> {
+        if depth > MAX_DEPTH {
+            return Err(JsonError::TooDeep(self.pos));
+        }
+
+        match self.peek() {
+            Some(b'"') => self.string().map(|_| ()),
+            Some(b'{') => self.object(|cursor, _| cursor.skip_value(depth + 1)),
+            Some(b'[') => self.array(|cursor| cursor.skip_value(depth + 1)),
+            Some(b'-' | b'0'..=b'9' | b't' | b'f' | b'n') => {
+                let start = self.pos;
+                while let Some(b'-' | b'+' | b'.' | b'0'..=b'9' | b'a'..=b'z' | b'E') =
+                    self.buf.get(self.pos)
+                {
+                    self.pos += 1;
+                }
+                let literal = &self.buf[start..self.pos];
+                if literal[0].is_ascii_alphabetic()
+                    && !matches!(literal, b"true" | b"false" | b"null")
+                {
+                    return Err(JsonError::Syntax(start));
+                }
+                Ok(())
+            }
+            _ => Err(JsonError::Syntax(self.pos)),
+        }
+    }
+
+    /// Iterates over the members of an object; `member` must consume the value.
+    fn object<F>(&mut self, mut member: F) -> Result<(), JsonError>
+    where
+        F: FnMut(&mut Self, &'a [u8]) -> Result<(), JsonError>,
+    {
+        self.expect(b'{')?;
+        if self.eat(b'}') {
+            return Ok(());
+        }
+
+        loop {
+            let key = self.string()?;
+            self.expect(b':')?;
+            member(self, key)?;
+            if !self.eat(b',') {
+                return self.expect(b'}');
+            }
+        }
+    }
+
+    /// Iterates over the elements of an array; `element` must consume the value.
+    fn array<F>(&mut self, mut element: F) -> Result<(), JsonError>
+    where
+        F: FnMut(&mut Self) -> Result<(), JsonError>,
+    {
+        self.expect(b'[')?;
+        if self.eat(b']') {
+            return Ok(());
+        }
+
+        loop {
+            element(self)?;
+            if !self.eat(b',') {
+                return self.expect(b']');
+            }
+        }
+    }
+
+    /// Parses a byte value written as a decimal or `0x` hexadecimal string.
+    fn byte(&mut self) -> Result<u8, JsonError> {
+        let start = self.pos;
+        let text = self.string()?;
+
+        let (digits, radix) = match text {
+            [b'0', b'x' | b'X', hex @ ..] => (hex, 16),
+            _ => (text, 10),
+        };
+
+        core::str::from_utf8(digits)
+            .ok()
+            .filter(|digits| !digits.is_empty())
+            .and_then(|digits| u8::from_str_radix(digits, radix).ok())
+            .ok_or(JsonError::InvalidNumber(start))
+    }
+}
+
+/// A validated `reg_config` JSON document.
+///
+/// # Description
+///
+/// Holds the position of the IIS2DULPX configuration array inside the document, so that the
+/// entries can be decoded lazily, possibly several times, without copying them.
+#[derive(Clone, Copy, Debug)]
+pub struct JsonProgram<'a> {
+    buf: &'a [u8],
+    configuration: usize,
+}
+
+impl<'a> JsonProgram<'a> {
+    /// Parses a `reg_config` document.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Self, JsonError>`: The program, or an error if the document is malformed, is
+    ///   not a `reg_config` version `2.0` document or has no configuration for the IIS2DULPX.
+    ///
+    /// # Description
+    ///
+    /// The whole document is checked for syntax. The entries of the configuration are only
+    /// checked when iterated, see [`JsonProgram::validate`].
+    pub fn parse(buf: &'a [u8]) -> Result<Self, JsonError> {
+        let mut cursor = Cursor { buf, pos: 0 };
+        let mut format_ok = false;
+        let mut configuration = None;
+
+        cursor.object(|cursor, key| match key {
+            b"json_format" => {
+                let (mut kind, mut version) = (None, None);
+                cursor.object(|cursor, key| match key {
+                    b"type" => cursor.string().map(|value| kind = Some(value)),
+                    b"version" => cursor.string().map(|value| version = Some(value)),
+                    _ => cursor.skip_value(1),
+                })?;
+                format_ok = kind == Some(b"reg_config") && version == Some(b"2.0");
+                Ok(())
+            }
+            b"sensors" => cursor.array(|cursor| {
+                let (mut named, mut config) = (false, None);
+                cursor.object(|cursor, key| match key {
+                    b"name" if cursor.peek() == Some(b'[') => cursor.array(|cursor| {
+                        named |= cursor.string()? == SENSOR_NAME.as_bytes();
+                        Ok(())
+                    }),
+                    b"name" => {
+                        named |= cursor.string()? == SENSOR_NAME.as_bytes();
+                        Ok(())
+                    }
+                    b"configuration" => {
+                        config = Some(cursor.pos);
+                        cursor.skip_value(2)
+                    }
+                    _ => cursor.skip_value(2),
+                })?;
+                if named && configuration.is_none() {
+                    configuration = config;
+                }
+                Ok(())
+            }),
+            _ => cursor.skip_value(1),
+        })?;
+
+        if cursor.peek().is_some() {
+            return Err(JsonError::Syntax(cursor.pos));
+        }
+        if !format_ok {
+            return Err(JsonError::Format);
+        }
+
+        Ok(Self {
+            buf,
+            configuration: configuration.ok_or(JsonError::SensorNotFound)?,
+        })
+    }
+
+    /// Returns an iterator decoding the configuration entries in order.
+    pub fn entries(&self) -> JsonEntries<'a> {
+        JsonEntries {
+            cursor: Cursor {
+                buf: self.buf,
+                pos: self.configuration,
+            },
+            state: EntriesState::Start,
+        }
+    }
+
+    /// Decodes all the entries and returns their number, or the first error.
+    pub fn validate(&self) -> Result<usize, JsonError> {
+        self.entries()
+            .try_fold(0, |count, entry| entry.map(|_| count + 1))
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum EntriesState {
+    Start,
+    Next,
+    Done,
+}
+
+/// Iterator over the entries of a [`JsonProgram`].
+///
+/// # Description
+///
+/// Yields `Err` once and stops at the first malformed entry.
+#[derive(Clone, Copy)]
+pub struct JsonEntries<'a> {
+    cursor: Cursor<'a>,
+    state: EntriesState,
+}
+
+impl JsonEntries<'_> {
+    fn entry(&mut self) -> Result<Option<UcfEntry>, JsonError> {
+        let cursor = &mut self.cursor;
+
+        match self.state {
+            EntriesState::Done => return Ok(None),
+            EntriesState::Start => {
+                cursor.expect(b'[')?;
+                if cursor.eat(b']') {
+                    return Ok(None);
+                }
+            }
+            EntriesState::Next => {
+                if !cursor.eat(b',') {
+                    cursor.expect(b']')?;
+                    return Ok(None);
+                }
+            }
+        }
+
+        let start = cursor.pos;
+        let (mut op, mut address, mut data) = (None, None, None);
+        cursor.object(|cursor, key| match key {
+            b"type" => cursor.string().map(|value| op = Some(value)),
+            b"address" => cursor.byte().map(|value| address = Some(value)),
+            b"data" => cursor.byte().map(|value| data = Some(value)),
+            _ => cursor.skip_value(1),
+        })?;
+
+        let entry = match (op, address, data) {
+            (Some(b"write"), Some(address), Some(data)) => UcfEntry::write(address, data),
+            (Some(b"delay"), _, Some(ms)) => UcfEntry::delay(ms),
+            _ => return Err(JsonError::UnsupportedOp(start)),
+        };
+
+        self.state = EntriesState::Next;
+        Ok(Some(entry))
+    }
+}
+
+impl Iterator for JsonEntries<'_> {
+    type Item = Result<UcfEntry, JsonError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let entry = self.entry();
+        if !matches!(entry, Ok(Some(_))) {
+            self.state = EntriesState::Done;
+        }
+
+        entry.transpose()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn minimal() {
+        let json = br#"{
+            "json_format": { "type": "reg_config", "version": "2.0" },
+            "sensors": [
+                { "name": ["OTHER"], "configuration": [] },
+                {
+                    "configuration": [
+                        { "type": "write", "address": "0x3F", "data": "0x80" },
+                        { "type": "delay", "data": "5" },
+                        { "type": "write", "address": "0x3f", "data": "0" }
+                    ],
+                    "name": ["IIS2DULPX"]
+                }
+            ]
+        }"#;
+
+        let program = JsonProgram::parse(json).unwrap();
+        let mut entries = program.entries();
+        assert_eq!(entries.next(), Some(Ok(UcfEntry::write(0x3F, 0x80))));
+        assert_eq!(entries.next(), Some(Ok(UcfEntry::delay(5))));
+        assert_eq!(entries.next(), Some(Ok(UcfEntry::write(0x3F, 0x00))));
+        assert_eq!(entries.next(), None);
+        assert_eq!(program.validate(), Ok(3));
+    }
+
+    #[test]
+    fn bsp_configurations() {
+        let vibration = include_bytes!("../../bsp/src/config/iis2dulpx_vibration_monitoring.json");
+        let program = JsonProgram::parse(vibration).unwrap();
+        assert_eq!(program.validate(), Ok(78));
+
+        let fourd =
+            include_bytes!("../../bsp/src/config/iis2dulpx_fourd_orientation_detection.json");
+        let program = JsonProgram::parse(fourd).unwrap();
+        assert_eq!(
+            program.entries().next(),
+            Some(Ok(UcfEntry::write(0x14, 0x00)))
+        );
+    }
+
+    #[test]
+    fn errors() {
+        let wrong_version = br#"{"json_format": {"type": "reg_config", "version": "1.0"}}"#;
+        assert_eq!(
+            JsonProgram::parse(wrong_version).unwrap_err(),
+            JsonError::Format
+        );
+
+        let no_sensor = br#"{"json_format": {"type": "reg_config", "version": "2.0"},
+            "sensors": [{"name": ["LIS2DUX12"], "configuration": []}]}"#;
+        assert_eq!(
+            JsonProgram::parse(no_sensor).unwrap_err(),
+            JsonError::SensorNotFound
+        );
+
+        let truncated = br#"{"json_format": {"type": "reg_config", "version": "2.0"}"#;
+        assert!(matches!(
+            JsonProgram::parse(truncated),
+            Err(JsonError::Syntax(_))
+        ));
+
+        let bad_entry = br#"{"json_format": {"type": "reg_config", "version": "2.0"},
+            "sensors": [{"name": "IIS2DULPX", "configuration": [
+                {"type": "write", "address": "0x10", "data": "0x100"}]}]}"#;
+        let program = JsonProgram::parse(bad_entry).unwrap();
+        let mut entries = program.entries();
+        assert!(matches!(
+            entries.next(),
+            Some(Err(JsonError::InvalidNumber(_)))
+        ));
+        assert_eq!(entries.next(), None);
+
+        let read = br#"{"json_format": {"type": "reg_config", "version": "2.0"},
+            "sensors": [{"name": "IIS2DULPX", "configuration": [
+                {"type": "read", "address": "0x10"}]}]}"#;
+        let program = JsonProgram::parse(read).unwrap();
+        assert!(matches!(
+            program.validate(),
+            Err(JsonError::UnsupportedOp(_))
+        ));
+    }
+}
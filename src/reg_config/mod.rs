@@ -0,0 +1,73 @@
+//! Register configuration programs.
+//!
+//! ST tools (MEMS Studio, the Unico GUI) export MLC and FSM configurations as register
+//! configuration files (UCF, or JSON with `"type": "reg_config"`): a flat sequence of register
+//! writes and delays to be replayed on the device. A program is represented here as a slice
+//! of [`UcfEntry`] and loaded with `load_reg_config`.
+//!
+//! With the `reg_config_json` feature, the [`json`] module parses the JSON export at runtime,
+//! so that a program stored in flash or received over the air can be loaded without being
+//! converted at build time.
+//!
+//! The [`binary`] module defines a compact container for programs, checked by CRC, which is
+//! loaded with `load_reg_config_bin`.
+//!
+//! On the host, the `iis2dulpx-config` tool (built with the `std` feature) lists, compares and
+//! converts programs; the [`export`] module holds the conversions it shares with build scripts.
+
+pub mod binary;
+#[cfg(feature = "std")]
+pub mod export;
+#[cfg(feature = "reg_config_json")]
+pub mod json;
+
+/// Represents the operations of a register configuration program.
+///
+/// # Variants
+///
+/// - `Write`: Writes `data` to the register at `address`.
+/// - `Delay`: Waits `data` milliseconds.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum UcfOp {
+    Write,
+    Delay,
+}
+
+/// Represents one line of a register configuration program.
+///
+/// # Fields
+///
+/// - `op: UcfOp`: The operation.
+/// - `address: u8`: Register address for `Write`, ignored for `Delay`.
+/// - `data: u8`: Value written for `Write`, duration in milliseconds for `Delay`.
+///
+/// # Description
+///
+/// Addresses are raw register addresses in the bank selected at that point of the program:
+/// programs switch to the embedded functions bank by writing `FUNC_CFG_ACCESS` themselves.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct UcfEntry {
+    pub op: UcfOp,
+    pub address: u8,
+    pub data: u8,
+}
+
+impl UcfEntry {
+    /// Creates a register write.
+    pub const fn write(address: u8, data: u8) -> Self {
+        Self {
+            op: UcfOp::Write,
+            address,
+            data,
+        }
+    }
+
+    /// Creates a delay of `ms` milliseconds.
+    pub const fn delay(ms: u8) -> Self {
+        Self {
+            op: UcfOp::Delay,
+            address: 0,
+            data: ms,
+        }
+    }
+}
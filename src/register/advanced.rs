@@ -4,6 +4,7 @@ use super::super::{
 };
 
 use bitfield_struct::bitfield;
+use derive_more::TryFrom;
 use st_mem_bank_macro::adv_register;
 
 /// Represents the register addresses for embedded advanced features page 0.
@@ -13,7 +14,8 @@ use st_mem_bank_macro::adv_register;
 /// `EMB_FUNC_EN` bit to 1 in the `CTRL4` register. The embedded functions must be enabled for these
 /// registers to become accessible.
 #[repr(u8)]
-#[derive(Clone, Copy, PartialEq)]
+#[derive(Clone, Copy, PartialEq, Debug, TryFrom)]
+#[try_from(repr)]
 pub enum EmbAdvReg {
     /// Address for the `EMB_ADV_PG0` register.
     EmbAdvPg0 = 0x00,
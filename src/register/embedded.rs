@@ -14,7 +14,8 @@ use st_mem_bank_macro::register;
 /// These registers are accessible when the `EMB_FUNC_REG_ACCESS` bit is set to 1 in the `FUNC_CFG_ACCESS` register
 /// and the `EMB_FUNC_EN` bit is set to 1 in the `CTRL4` register.
 #[repr(u8)]
-#[derive(Clone, Copy, PartialEq)]
+#[derive(Clone, Copy, PartialEq, Debug, TryFrom)]
+#[try_from(repr)]
 pub enum EmbReg {
     /// Address for the `PAGE_SEL` register (R/W).
     PageSel = 0x02,
@@ -1176,7 +1177,9 @@ pub struct EmbeddedStatus {
 /// # Description
 ///
 /// This struct encapsulates the embedded function interrupt signals routing configuration, allowing
-/// customization of various embedded function interrupt sources.
+/// customization of various embedded function interrupt sources. The FSM programs and MLC decision
+/// trees are routed separately, with `fsm_int1_route_set`, `mlc_int1_route_set` and their INT2
+/// counterparts, so that building this struct with `..Default::default()` keeps their routing.
 #[derive(Default)]
 pub struct EmbPinIntRoute {
     pub step_det: u8,
@@ -1204,6 +1207,28 @@ pub struct StpcntMode {
     pub step_counter_in_fifo: u8,
 }
 
+/// Represents the batching of embedded function results in the FIFO.
+///
+/// # Fields
+///
+/// - `step_counter: u8`: Batches step counter values (`step_counter_fifo_en`).
+/// - `mlc: u8`: Batches MLC decision tree results (`mlc_fifo_en`).
+/// - `mlc_filter_feature: u8`: Batches MLC filter and feature values
+///   (`mlc_filter_feature_fifo_en`).
+/// - `fsm: u8`: Batches FSM results (`fsm_fifo_en`).
+///
+/// # Description
+///
+/// This struct collects all the fields of the `EMB_FUNC_FIFO_EN` register. The batched records
+/// are decoded as `Steps`, `MlcResult`, `MlcFilter`/`MlcFeature` and `FsmResult` FIFO samples.
+#[derive(Clone, Copy, PartialEq, Default, Debug)]
+pub struct EmbFifoBatching {
+    pub step_counter: u8,
+    pub mlc: u8,
+    pub mlc_filter_feature: u8,
+    pub fsm: u8,
+}
+
 /// Represents the embedded interrupt configuration modes.
 ///
 /// # Variants
@@ -14,7 +14,8 @@ use st_mem_bank_macro::register;
 /// This enum is used to specify the addresses of various registers within the device, allowing for
 /// read and write operations as specified.
 #[repr(u8)]
-#[derive(Clone, Copy, PartialEq)]
+#[derive(Clone, Copy, PartialEq, Debug, TryFrom)]
+#[try_from(repr)]
 pub enum Reg {
     /// Address for the `EXT_CLK_CFG` register (R/W).
     ExtClkCfg = 0x08,
@@ -1894,7 +1895,7 @@ pub struct Status {
 ///
 /// This struct encapsulates the sensor mode configuration, including settings for ODR, FS, and BW.
 /// It is used to configure and retrieve the sensor's operational mode.
-#[derive(Default)]
+#[derive(Clone, Copy, PartialEq, Default, Debug)]
 pub struct Md {
     pub odr: Odr,
     pub fs: Fs,
@@ -2140,6 +2141,35 @@ pub struct AhQvarMode {
     pub ah_qvar_gain: AhQvarGain,
 }
 
+/// Represents the configuration of the combined accelerometer and AH_QVAR acquisition.
+///
+/// # Fields
+///
+/// - `md: Md`: Accelerometer mode. The ODR must be a low-power or high-performance one.
+/// - `ah_qvar_notch_en: u8`: Indicates if the notch filter is enabled.
+/// - `ah_qvar_notch: AhQvarNotch`: Specifies the notch filter cutoff frequency.
+/// - `ah_qvar_zin: AhQvarZin`: Specifies the input impedance.
+/// - `ah_qvar_gain: AhQvarGain`: Specifies the gain setting.
+/// - `fifo_operation: FifoOperation`: FIFO mode used to batch paired samples, `FifoOff` to
+///   read them from the output registers only.
+///
+/// # Description
+///
+/// Used by `qvar_acquisition_set` to configure in one step the accelerometer, the
+/// temperature/AH_QVAR acquisition chain, the AH_QVAR front-end and the FIFO 12-bit format
+/// (`xl_only = 0`), in which each word carries an acceleration sample together with the
+/// AH_QVAR value under the `XlAndQvar` tag. While AH_QVAR is enabled the temperature is not
+/// available, as both share the same output.
+#[derive(Clone, Copy, Default)]
+pub struct QvarAcquisition {
+    pub md: Md,
+    pub ah_qvar_notch_en: u8,
+    pub ah_qvar_notch: AhQvarNotch,
+    pub ah_qvar_zin: AhQvarZin,
+    pub ah_qvar_gain: AhQvarGain,
+    pub fifo_operation: FifoOperation,
+}
+
 /// Represents the configuration settings for the 4D/6D detection function.
 ///
 /// # Fields
@@ -2248,7 +2278,9 @@ pub struct OuttData {
 /// # Description
 ///
 /// This struct encapsulates temperature data, providing both raw and converted values.
-#[derive(Default)]
+/// When read from the FIFO, `raw` is the sign-extended 12-bit packed value and must be
+/// converted with `from_fifo_lsb_to_celsius` instead of `from_lsb_to_celsius`.
+#[derive(Clone, Copy, PartialEq, Default, Debug)]
 pub struct Heat {
     pub deg_c: f32,
     pub raw: i16,
@@ -2270,7 +2302,7 @@ pub struct Heat {
 ///
 /// This struct encapsulates the FIFO mode configuration, allowing customization of operation mode,
 /// storage depth, watermark, and batching settings.
-#[derive(Default)]
+#[derive(Clone, Copy, Default, Debug)]
 pub struct FifoMode {
     pub operation: FifoOperation,
     pub store: Store,
@@ -2278,6 +2310,28 @@ pub struct FifoMode {
     pub cfg_change_in_fifo: u8,
 }
 
+/// Represents the FIFO fill status.
+///
+/// # Fields
+///
+/// - `level: u8`: Number of unread entries in the FIFO (`FSS`).
+/// - `wtm: u8`: Indicates that the level is equal to or higher than the watermark.
+/// - `full: u8`: Indicates that the FIFO holds `FIFO_DEPTH` entries.
+/// - `ovr: u8`: Indicates that the FIFO is full and at least one entry has been overwritten or
+///   dropped.
+///
+/// # Description
+///
+/// This struct collects the `FIFO_STATUS1` and `FIFO_STATUS2` registers, read together by
+/// `fifo_status_get` so that the flags and the level refer to the same instant.
+#[derive(Clone, Copy, PartialEq, Default, Debug)]
+pub struct FifoStatus {
+    pub level: u8,
+    pub wtm: u8,
+    pub full: u8,
+    pub ovr: u8,
+}
+
 /// Represents batching information for the FIFO.
 ///
 /// # Fields
@@ -2289,35 +2343,45 @@ pub struct FifoMode {
 ///
 /// This struct encapsulates batching information for the FIFO, allowing customization of timestamp
 /// decimation and accelerometer data rate.
-#[derive(Default)]
+#[derive(Clone, Copy, PartialEq, Default, Debug)]
 pub struct Batch {
     pub dec_ts: DecTs,
     pub bdr_xl: BdrXl,
 }
 
-/// Represents the processed FIFO data.
+/// Represents a raw FIFO entry.
 ///
 /// # Fields
 ///
-/// - `tag: u8`: The sensor tag.
-/// - `xl: [Xl; 2]`: Accelerometer data for two samples.
-/// - `ah_qvar: AhQvar`: AH_QVAR data.
-/// - `heat: Heat`: Temperature data.
-/// - `pedo: Pedo`: Pedometer data.
-/// - `cfg_chg: CfgChg`: Configuration change data.
+/// - `tag: u8`: The sensor tag (`TAG_SENSOR` field of `FIFO_DATA_OUT_TAG`).
+/// - `data: [u8; 6]`: The content of the `FIFO_DATA_OUT_X_L` to `FIFO_DATA_OUT_Z_H` registers.
 ///
 /// # Description
 ///
-/// This struct encapsulates the processed FIFO data, including accelerometer, AH_QVAR, temperature,
-/// pedometer, and configuration change information.
-#[derive(Default)]
-pub struct FifoData {
+/// This struct holds an undecoded FIFO entry as returned by `fifo_record_get` and
+/// `fifo_read_batch`.
+#[derive(Clone, Copy, PartialEq, Default, Debug)]
+pub struct FifoRecord {
     pub tag: u8,
-    pub xl: [Xl; 2],
-    pub ah_qvar: AhQvar,
-    pub heat: Heat,
-    pub pedo: Pedo,
-    pub cfg_chg: CfgChg,
+    pub data: [u8; 6],
+}
+
+impl FifoRecord {
+    /// Returns the sensor tag, `FifoEmpty` if unknown.
+    pub fn sensor_tag(&self) -> FifoSensorTag {
+        FifoSensorTag::try_from(self.tag).unwrap_or_default()
+    }
+
+    /// Builds a record from the `FIFO_DATA_OUT_TAG` byte followed by the six data bytes.
+    pub fn from_bytes(buff: &[u8; 7]) -> Self {
+        let mut data = [0; 6];
+        data.copy_from_slice(&buff[1..]);
+
+        Self {
+            tag: FifoDataOutTag::from_bits(buff[0]).tag_sensor(),
+            data,
+        }
+    }
 }
 
 /// Represents accelerometer data.
@@ -2330,7 +2394,7 @@ pub struct FifoData {
 /// # Description
 ///
 /// This struct encapsulates accelerometer data, providing both raw and converted values for each axis.
-#[derive(Default)]
+#[derive(Clone, Copy, PartialEq, Default, Debug)]
 pub struct Xl {
     pub mg: [f32; 3],
     pub raw: [i16; 3],
@@ -2346,12 +2410,29 @@ pub struct Xl {
 /// # Description
 ///
 /// This struct encapsulates AH_QVAR data, providing both raw and converted voltage values.
-#[derive(Default)]
+#[derive(Clone, Copy, PartialEq, Default, Debug)]
 pub struct AhQvar {
     pub mv: f32,
     pub raw: i16,
 }
 
+/// Represents a paired accelerometer and AH_QVAR sample.
+///
+/// # Fields
+///
+/// - `xl: Xl`: Accelerometer data.
+/// - `ah_qvar: AhQvar`: AH_QVAR data acquired with the acceleration sample.
+///
+/// # Description
+///
+/// Returned by `xl_qvar_data_get` or extracted from an `XlAndQvar` FIFO record with
+/// `XlQvarData::from_fifo`.
+#[derive(Clone, Copy, PartialEq, Default, Debug)]
+pub struct XlQvarData {
+    pub xl: Xl,
+    pub ah_qvar: AhQvar,
+}
+
 /// Represents pedometer data.
 ///
 /// # Fields
@@ -2362,43 +2443,110 @@ pub struct AhQvar {
 /// # Description
 ///
 /// This struct encapsulates pedometer data, providing step count and timestamp information.
-#[derive(Default)]
+#[derive(Clone, Copy, PartialEq, Default, Debug)]
 pub struct Pedo {
     pub steps: u32,
     pub timestamp: u32,
 }
 
+/// Represents a Machine Learning Core result batched in the FIFO.
+///
+/// # Fields
+///
+/// - `index: u8`: Index of the decision tree that produced the result (0 to 3).
+/// - `class: u8`: Output class of the decision tree, as in `MLCx_SRC`.
+/// - `timestamp: u32`: Timestamp of the result, in 10 µs ticks.
+///
+/// # Description
+///
+/// A record is written to the FIFO each time a decision tree output changes, when
+/// `mlc_fifo_en` is set.
+#[derive(Clone, Copy, PartialEq, Default, Debug)]
+pub struct MlcFifoResult {
+    pub index: u8,
+    pub class: u8,
+    pub timestamp: u32,
+}
+
+/// Represents a Machine Learning Core filter or feature value batched in the FIFO.
+///
+/// # Fields
+///
+/// - `id: u16`: Identifier of the filter or feature, as assigned in the MLC configuration.
+/// - `raw: u16`: The half-precision value.
+/// - `value: f32`: The value converted to single precision.
+///
+/// # Description
+///
+/// Filter and feature values are written to the FIFO when `mlc_filter_feature_fifo_en` is set.
+#[derive(Clone, Copy, PartialEq, Default, Debug)]
+pub struct MlcFifoValue {
+    pub id: u16,
+    pub raw: u16,
+    pub value: f32,
+}
+
+/// Represents a Finite State Machine result batched in the FIFO.
+///
+/// # Fields
+///
+/// - `index: u8`: Index of the FSM program that produced the result (0 to 7).
+/// - `output: u8`: Content of the program output register, as in `FSM_OUTSx`.
+/// - `timestamp: u32`: Timestamp of the result, in 10 µs ticks.
+///
+/// # Description
+///
+/// A record is written to the FIFO each time a program generates an interrupt, when
+/// `fsm_fifo_en` is set.
+#[derive(Clone, Copy, PartialEq, Default, Debug)]
+pub struct FsmFifoResult {
+    pub index: u8,
+    pub output: u8,
+    pub timestamp: u32,
+}
+
 /// Represents configuration change data.
 ///
 /// # Fields
 ///
 /// - `cfg_change: u8`: Indicates if a configuration change occurred (1 bit).
-/// - `odr: u8`: Output data rate (4 bits).
-/// - `bw: u8`: Bandwidth (2 bits).
+/// - `odr: Odr`: Output data rate, including the low-power/high-performance selection.
+/// - `bw: Bw`: Bandwidth.
 /// - `lp_hp: u8`: Low-power/high-performance mode (1 bit).
 /// - `qvar_en: u8`: AH_QVAR enable status (1 bit).
-/// - `fs: u8`: Full-scale range (2 bits).
-/// - `dec_ts: u8`: Decimation for timestamp batching (2 bits).
-/// - `odr_xl_batch: u8`: Output data rate for accelerometer batching (1 bit).
+/// - `fs: Fs`: Full-scale range.
+/// - `dec_ts: DecTs`: Decimation for timestamp batching.
+/// - `odr_xl_batch: BdrXl`: Batch data rate for the accelerometer.
 /// - `timestamp: u32`: The timestamp associated with the configuration change.
 ///
 /// # Description
 ///
 /// This struct encapsulates configuration change data, providing detailed information about changes
 /// in sensor settings and associated timestamps.
-#[derive(Default)]
+#[derive(Clone, Copy, PartialEq, Default, Debug)]
 pub struct CfgChg {
     pub cfg_change: u8,
-    pub odr: u8,
-    pub bw: u8,
+    pub odr: Odr,
+    pub bw: Bw,
     pub lp_hp: u8,
     pub qvar_en: u8,
-    pub fs: u8,
-    pub dec_ts: u8,
-    pub odr_xl_batch: u8,
+    pub fs: Fs,
+    pub dec_ts: DecTs,
+    pub odr_xl_batch: BdrXl,
     pub timestamp: u32,
 }
 
+impl CfgChg {
+    /// Returns the sensor mode in effect after the configuration change.
+    pub fn md(&self) -> Md {
+        Md {
+            odr: self.odr,
+            fs: self.fs,
+            bw: self.bw,
+        }
+    }
+}
+
 /// Represents the bus activity selection for the I3C interface.
 ///
 /// # Variants
@@ -2749,7 +2897,7 @@ pub enum Axis {
 /// This enum is used to specify the FIFO operation mode, allowing for various configurations such
 /// as bypass, FIFO, and stream modes.
 #[repr(u8)]
-#[derive(Clone, Copy, PartialEq, Default, TryFrom)]
+#[derive(Clone, Copy, PartialEq, Default, Debug, TryFrom)]
 #[try_from(repr)]
 pub enum FifoOperation {
     #[default]
@@ -2773,7 +2921,7 @@ pub enum FifoOperation {
 ///
 /// This enum is used to specify the storage depth for the FIFO, allowing for 1X or 2X configurations.
 #[repr(u8)]
-#[derive(Clone, Copy, PartialEq, Default, TryFrom)]
+#[derive(Clone, Copy, PartialEq, Default, Debug, TryFrom)]
 #[try_from(repr)]
 pub enum Store {
     #[default]
@@ -2794,7 +2942,7 @@ pub enum Store {
 ///
 /// This enum is used to specify the decimation for timestamp batching in the FIFO.
 #[repr(u8)]
-#[derive(Clone, Copy, PartialEq, Default, TryFrom)]
+#[derive(Clone, Copy, PartialEq, Default, Debug, TryFrom)]
 #[try_from(repr)]
 pub enum DecTs {
     #[default]
@@ -2821,7 +2969,7 @@ pub enum DecTs {
 ///
 /// This enum is used to specify the accelerometer batch data rate for the FIFO.
 #[repr(u8)]
-#[derive(Clone, Copy, PartialEq, Default, TryFrom)]
+#[derive(Clone, Copy, PartialEq, Default, Debug, TryFrom)]
 #[try_from(repr)]
 pub enum BdrXl {
     #[default]
@@ -2926,7 +3074,7 @@ pub enum DataReadyMode {
 ///
 /// This enum is used to specify the ODR for sensor operations.
 #[repr(u8)]
-#[derive(Clone, Copy, PartialEq, Default)]
+#[derive(Clone, Copy, PartialEq, Default, Debug)]
 pub enum Odr {
     #[default]
     Off = 0x00,
@@ -3021,6 +3169,28 @@ impl Odr {
             _ => Odr::Off,
         }
     }
+
+    /// Returns the nominal output data rate in Hz.
+    ///
+    /// # Returns
+    ///
+    /// * `Option<f32>`: The sampling frequency, or `None` when the ODR is `Off` or the
+    ///   sampling is triggered (`TrigPin`, `TrigSw`) and therefore has no fixed rate.
+    pub fn hz(&self) -> Option<f32> {
+        match self {
+            Odr::_1_6hzUlp => Some(1.6),
+            Odr::_3hzUlp => Some(3.0),
+            Odr::_25hzUlp | Odr::_25hzLp | Odr::_25hzHp => Some(25.0),
+            Odr::_6hzLp | Odr::_6hzHp => Some(6.0),
+            Odr::_12_5hzLp | Odr::_12_5hzHp => Some(12.5),
+            Odr::_50hzLp | Odr::_50hzHp => Some(50.0),
+            Odr::_100hzLp | Odr::_100hzHp => Some(100.0),
+            Odr::_200hzLp | Odr::_200hzHp => Some(200.0),
+            Odr::_400hzLp | Odr::_400hzHp => Some(400.0),
+            Odr::_800hzLp | Odr::_800hzHp => Some(800.0),
+            Odr::Off | Odr::TrigPin | Odr::TrigSw => None,
+        }
+    }
 }
 
 /// Represents the full-scale (FS) options.
@@ -3036,7 +3206,7 @@ impl Odr {
 ///
 /// This enum is used to specify the FS for sensor operations.
 #[repr(u8)]
-#[derive(Clone, Copy, PartialEq, Default, TryFrom)]
+#[derive(Clone, Copy, PartialEq, Default, Debug, TryFrom)]
 #[try_from(repr)]
 pub enum Fs {
     #[default]
@@ -3059,7 +3229,7 @@ pub enum Fs {
 ///
 /// This enum is used to specify the bandwidth for sensor operations.
 #[repr(u8)]
-#[derive(Clone, Copy, PartialEq, Default, TryFrom)]
+#[derive(Clone, Copy, PartialEq, Default, Debug, TryFrom)]
 #[try_from(repr)]
 pub enum Bw {
     #[default]
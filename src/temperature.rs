@@ -0,0 +1,232 @@
+//! Temperature processing.
+//!
+//! The sensor exposes its die temperature in two formats:
+//!
+//! * the 16-bit `OUT_T_AH_QVAR` register, read with `outt_data_get`;
+//! * the 12-bit value packed in FIFO words when `xl_only` is disabled, decoded by
+//!   `fifo_data_get` with the `XlTempTag`.
+//!
+//! This module converts both formats with the proper sensitivity, applies a user one-point
+//! offset calibration, averages readings and compensates acceleration offsets with linear
+//! temperature coefficients.
+
+use super::driver::{from_fifo_lsb_to_celsius, from_lsb_to_celsius};
+use super::filter::{Filter, MovingAverage};
+use super::register::main::XlData;
+
+/// Represents the origin of a raw temperature value.
+///
+/// # Variants
+///
+/// - `Register`: 16-bit value from the `OUT_T_AH_QVAR` register.
+/// - `FifoPacked`: Sign-extended 12-bit value from a FIFO word in 12-bit format.
+///
+/// # Description
+///
+/// The two formats share the same offset but differ in sensitivity by a factor of 16.
+#[derive(Clone, Copy, PartialEq, Default, Debug)]
+pub enum TempSource {
+    #[default]
+    Register,
+    FifoPacked,
+}
+
+impl TempSource {
+    /// Converts a raw value from this source to degrees Celsius.
+    pub fn to_celsius(&self, raw: i16) -> f32 {
+        match self {
+            TempSource::Register => from_lsb_to_celsius(raw),
+            TempSource::FifoPacked => from_fifo_lsb_to_celsius(raw),
+        }
+    }
+}
+
+/// Represents a one-point temperature calibration.
+///
+/// # Fields
+///
+/// - `offset_c: f32`: Offset in degrees Celsius added to every converted reading.
+///
+/// # Description
+///
+/// The offset is obtained by comparing a sensor reading with a reference thermometer at a
+/// single operating point, see [`TempCalibration::from_reference`].
+#[derive(Clone, Copy, PartialEq, Default, Debug)]
+pub struct TempCalibration {
+    pub offset_c: f32,
+}
+
+impl TempCalibration {
+    /// Computes the calibration from a sensor reading and the reference temperature
+    /// measured at the same time.
+    pub fn from_reference(measured_c: f32, reference_c: f32) -> Self {
+        Self {
+            offset_c: reference_c - measured_c,
+        }
+    }
+
+    /// Applies the calibration to a temperature in degrees Celsius.
+    pub fn apply(&self, deg_c: f32) -> f32 {
+        deg_c + self.offset_c
+    }
+}
+
+/// Converts, calibrates and averages temperature readings.
+///
+/// # Fields
+///
+/// - `calibration: TempCalibration`: User offset calibration.
+///
+/// # Description
+///
+/// Raw values from either source are converted with the matching sensitivity, corrected by
+/// `calibration` and averaged over the last `N` readings.
+#[derive(Clone, Copy, Debug)]
+pub struct Thermometer<const N: usize> {
+    pub calibration: TempCalibration,
+    average: MovingAverage<N>,
+    last: Option<f32>,
+}
+
+impl<const N: usize> Thermometer<N> {
+    /// Creates a thermometer with the given calibration.
+    pub const fn new(calibration: TempCalibration) -> Self {
+        Self {
+            calibration,
+            average: MovingAverage::new(),
+            last: None,
+        }
+    }
+
+    /// Adds a raw reading and returns the averaged, calibrated temperature in degrees Celsius.
+    ///
+    /// # Arguments
+    ///
+    /// * `raw`: Raw temperature value.
+    /// * `source`: Format of `raw`.
+    pub fn update(&mut self, raw: i16, source: TempSource) -> f32 {
+        self.update_celsius(source.to_celsius(raw))
+    }
+
+    /// Adds an already converted, uncalibrated reading in degrees Celsius and returns the
+    /// averaged, calibrated temperature.
+    pub fn update_celsius(&mut self, deg_c: f32) -> f32 {
+        let avg = self.average.process(self.calibration.apply(deg_c));
+        self.last = Some(avg);
+        avg
+    }
+
+    /// Returns the last averaged temperature, if any reading has been added.
+    pub fn temperature(&self) -> Option<f32> {
+        self.last
+    }
+
+    /// Discards the averaging history.
+    pub fn reset(&mut self) {
+        self.average.reset();
+        self.last = None;
+    }
+}
+
+impl<const N: usize> Default for Thermometer<N> {
+    fn default() -> Self {
+        Self::new(TempCalibration::default())
+    }
+}
+
+/// Represents linear temperature compensation of acceleration offsets.
+///
+/// # Fields
+///
+/// - `reference_c: f32`: Temperature in degrees Celsius at which the offsets were characterized.
+/// - `coeff_mg_per_c: [f32; 3]`: Offset drift of the X, Y and Z axes in mg/°C.
+///
+/// # Description
+///
+/// The compensated acceleration is `mg - coeff_mg_per_c * (deg_c - reference_c)`. The
+/// coefficients are application specific and are usually measured over the operating
+/// temperature range on the final assembly.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct TempCompensation {
+    pub reference_c: f32,
+    pub coeff_mg_per_c: [f32; 3],
+}
+
+impl Default for TempCompensation {
+    fn default() -> Self {
+        Self {
+            reference_c: 25.0,
+            coeff_mg_per_c: [0.0; 3],
+        }
+    }
+}
+
+impl TempCompensation {
+    /// Compensates one `[x, y, z]` sample in milli-g at temperature `deg_c`.
+    pub fn apply(&self, mg: &mut [f32; 3], deg_c: f32) {
+        let delta = deg_c - self.reference_c;
+        for (value, coeff) in mg.iter_mut().zip(self.coeff_mg_per_c) {
+            *value -= coeff * delta;
+        }
+    }
+
+    /// Compensates the milli-g values returned by `xl_data_get` at temperature `deg_c`.
+    pub fn apply_xl_data(&self, data: &mut XlData, deg_c: f32) {
+        self.apply(&mut data.mg, deg_c);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sources() {
+        assert_eq!(TempSource::Register.to_celsius(0), 25.0);
+        assert_eq!(TempSource::Register.to_celsius(3555), 35.0);
+        // The 12-bit FIFO value is the 16-bit value shifted right by 4.
+        assert_eq!(
+            TempSource::FifoPacked.to_celsius(3555 >> 4),
+            25.0 + 3552.0 / 355.5
+        );
+        assert_eq!(TempSource::FifoPacked.to_celsius(-16), 25.0 - 256.0 / 355.5);
+    }
+
+    #[test]
+    fn calibration_and_average() {
+        let calibration = TempCalibration::from_reference(27.0, 25.5);
+        assert_eq!(calibration.offset_c, -1.5);
+        assert_eq!(calibration.apply(30.0), 28.5);
+
+        let mut thermometer = Thermometer::<4>::new(calibration);
+        assert_eq!(thermometer.temperature(), None);
+        assert_eq!(thermometer.update(3555, TempSource::Register), 33.5);
+        assert_eq!(thermometer.update_celsius(32.0), 32.0);
+        assert_eq!(thermometer.temperature(), Some(32.0));
+
+        for _ in 0..4 {
+            thermometer.update_celsius(21.5);
+        }
+        assert_eq!(thermometer.temperature(), Some(20.0));
+
+        thermometer.reset();
+        assert_eq!(thermometer.temperature(), None);
+        assert_eq!(thermometer.update_celsius(26.5), 25.0);
+    }
+
+    #[test]
+    fn compensation() {
+        let compensation = TempCompensation {
+            reference_c: 25.0,
+            coeff_mg_per_c: [0.5, -1.0, 0.0],
+        };
+
+        let mut mg = [10.0, 20.0, 1000.0];
+        compensation.apply(&mut mg, 35.0);
+        assert_eq!(mg, [5.0, 30.0, 1000.0]);
+
+        let mut mg = [10.0, 20.0, 1000.0];
+        compensation.apply(&mut mg, 25.0);
+        assert_eq!(mg, [10.0, 20.0, 1000.0]);
+    }
+}
@@ -0,0 +1,302 @@
+//! Sensor timestamp handling.
+//!
+//! The embedded timestamp counter is a 32-bit register incremented every 10 µs. It wraps
+//! around after about 11.9 hours and is cleared by a software reset. This module provides:
+//!
+//! * [`SensorTime`]: a tick count with conversions to microseconds and [`Duration`].
+//! * [`TimestampExtender`]: turns successive raw `u32` readings into a monotonic 64-bit
+//!   timeline by accounting for counter rollover.
+//! * [`TimeCorrelator`]: estimates offset and drift between the sensor timeline and an MCU
+//!   time source, so that sensor samples can be placed on the system timeline.
+
+use core::time::Duration;
+
+/// Resolution of the sensor timestamp counter in microseconds.
+pub const TIMESTAMP_TICK_US: u64 = 10;
+
+/// Represents a point on the sensor timeline.
+///
+/// # Fields
+///
+/// - `0: u64`: Number of 10 µs ticks.
+///
+/// # Description
+///
+/// Values built with [`SensorTime::from_raw`] carry the raw 32-bit counter value; use a
+/// [`TimestampExtender`] to obtain values that keep increasing across counter rollovers.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default, Debug)]
+pub struct SensorTime(pub u64);
+
+impl SensorTime {
+    /// Creates a `SensorTime` from the raw value returned by `timestamp_raw_get` or stored
+    /// in FIFO timestamp records.
+    pub const fn from_raw(raw: u32) -> Self {
+        Self(raw as u64)
+    }
+
+    /// Returns the number of 10 µs ticks.
+    pub const fn ticks(&self) -> u64 {
+        self.0
+    }
+
+    /// Returns the time in microseconds.
+    pub const fn as_micros(&self) -> u64 {
+        self.0 * TIMESTAMP_TICK_US
+    }
+
+    /// Returns the time in milliseconds, truncated.
+    pub const fn as_millis(&self) -> u64 {
+        self.as_micros() / 1000
+    }
+
+    /// Returns the time elapsed since the start of the sensor timeline.
+    pub const fn as_duration(&self) -> Duration {
+        Duration::from_micros(self.as_micros())
+    }
+
+    /// Returns the time elapsed from `earlier` to `self`, or zero if `earlier` is later.
+    pub const fn duration_since(&self, earlier: SensorTime) -> Duration {
+        Duration::from_micros(self.0.saturating_sub(earlier.0) * TIMESTAMP_TICK_US)
+    }
+}
+
+impl From<u32> for SensorTime {
+    fn from(raw: u32) -> Self {
+        Self::from_raw(raw)
+    }
+}
+
+impl From<SensorTime> for Duration {
+    fn from(time: SensorTime) -> Self {
+        time.as_duration()
+    }
+}
+
+/// Extends the 32-bit sensor timestamp to a monotonic 64-bit timeline.
+///
+/// # Description
+///
+/// Each raw value lower than the previous one is interpreted as a counter rollover. The
+/// extender must therefore be fed at least once per rollover period (about 11.9 hours),
+/// and must be [reset](TimestampExtender::reset) after a sensor software reset, which
+/// clears the counter.
+#[derive(Clone, Copy, Default, Debug)]
+pub struct TimestampExtender {
+    last: Option<u32>,
+    high: u64,
+}
+
+impl TimestampExtender {
+    /// Creates an extender with no history.
+    pub const fn new() -> Self {
+        Self {
+            last: None,
+            high: 0,
+        }
+    }
+
+    /// Extends a raw 32-bit timestamp.
+    ///
+    /// # Arguments
+    ///
+    /// * `raw`: Raw timestamp value, in 10 µs ticks.
+    ///
+    /// # Returns
+    ///
+    /// * `SensorTime`: The extended, monotonic timestamp.
+    pub fn extend(&mut self, raw: u32) -> SensorTime {
+        if self.last.is_some_and(|last| raw < last) {
+            self.high += 1 << 32;
+        }
+        self.last = Some(raw);
+
+        SensorTime(self.high | raw as u64)
+    }
+
+    /// Returns the last extended timestamp, if any.
+    pub fn last(&self) -> Option<SensorTime> {
+        self.last.map(|raw| SensorTime(self.high | raw as u64))
+    }
+
+    /// Clears the rollover history.
+    pub fn reset(&mut self) {
+        *self = Self::new();
+    }
+}
+
+/// Estimates the relation between the sensor timeline and an MCU time source.
+///
+/// # Description
+///
+/// The correlator is fed with pairs of sensor and host timestamps captured at the same
+/// instant (for example by reading `timestamp_raw_get` and the MCU clock back to back, or
+/// on a data-ready interrupt). A least-squares line is fitted through the pairs:
+///
+/// `host_us = host_ref + offset_us + rate * (sensor_us - sensor_ref)`
+///
+/// where `rate` is the ratio between the host and sensor clock frequencies. With a single
+/// pair the rate is assumed to be 1. Sensor timestamps should come from a
+/// [`TimestampExtender`] so that the timeline is monotonic.
+#[derive(Clone, Copy, Default, Debug)]
+pub struct TimeCorrelator {
+    reference: Option<(u64, u64)>,
+    n: f64,
+    sum_x: f64,
+    sum_y: f64,
+    sum_xx: f64,
+    sum_xy: f64,
+}
+
+impl TimeCorrelator {
+    /// Creates an empty correlator.
+    pub const fn new() -> Self {
+        Self {
+            reference: None,
+            n: 0.0,
+            sum_x: 0.0,
+            sum_y: 0.0,
+            sum_xx: 0.0,
+            sum_xy: 0.0,
+        }
+    }
+
+    /// Adds a pair of timestamps captured at the same instant.
+    ///
+    /// # Arguments
+    ///
+    /// * `sensor`: Sensor timestamp.
+    /// * `host_us`: MCU time in microseconds.
+    pub fn add_pair(&mut self, sensor: SensorTime, host_us: u64) {
+        let (sensor_ref, host_ref) = *self.reference.get_or_insert((sensor.as_micros(), host_us));
+
+        let x = sensor.as_micros() as f64 - sensor_ref as f64;
+        let y = host_us as f64 - host_ref as f64;
+
+        self.n += 1.0;
+        self.sum_x += x;
+        self.sum_y += y;
+        self.sum_xx += x * x;
+        self.sum_xy += x * y;
+    }
+
+    /// Returns the number of pairs collected so far.
+    pub fn pairs(&self) -> u32 {
+        self.n as u32
+    }
+
+    /// Returns the ratio between the host and sensor clock rates.
+    pub fn rate(&self) -> f64 {
+        let den = self.n * self.sum_xx - self.sum_x * self.sum_x;
+        if self.n < 2.0 || den == 0.0 {
+            return 1.0;
+        }
+
+        (self.n * self.sum_xy - self.sum_x * self.sum_y) / den
+    }
+
+    /// Returns the sensor clock drift relative to the host clock, in ppm.
+    ///
+    /// A positive value means that the sensor clock runs slower than the host clock.
+    pub fn drift_ppm(&self) -> f64 {
+        (self.rate() - 1.0) * 1e6
+    }
+
+    /// Returns the offset in microseconds to add to a sensor time to obtain host time,
+    /// evaluated at the first collected pair.
+    pub fn offset_us(&self) -> Option<i64> {
+        let (sensor_ref, host_ref) = self.reference?;
+
+        Some(host_ref as i64 - sensor_ref as i64 + self.intercept() as i64)
+    }
+
+    /// Maps a sensor timestamp onto the host timeline.
+    ///
+    /// # Returns
+    ///
+    /// * `Option<u64>`: Host time in microseconds, or `None` if no pair has been collected
+    ///   or the result would be negative.
+    pub fn to_host_us(&self, sensor: SensorTime) -> Option<u64> {
+        let (sensor_ref, host_ref) = self.reference?;
+
+        let x = sensor.as_micros() as f64 - sensor_ref as f64;
+        let host = host_ref as f64 + self.intercept() + self.rate() * x;
+
+        (host >= 0.0).then_some(host as u64)
+    }
+
+    /// Discards all collected pairs.
+    pub fn reset(&mut self) {
+        *self = Self::new();
+    }
+
+    fn intercept(&self) -> f64 {
+        if self.n == 0.0 {
+            return 0.0;
+        }
+
+        (self.sum_y - self.rate() * self.sum_x) / self.n
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn conversions() {
+        let time = SensorTime::from_raw(123_456);
+        assert_eq!(time.as_micros(), 1_234_560);
+        assert_eq!(time.as_millis(), 1_234);
+        assert_eq!(Duration::from(time), Duration::from_micros(1_234_560));
+        assert_eq!(
+            time.duration_since(SensorTime::from_raw(123_356)),
+            Duration::from_millis(1)
+        );
+        assert_eq!(SensorTime::from_raw(0).duration_since(time), Duration::ZERO);
+    }
+
+    #[test]
+    fn rollover() {
+        let mut extender = TimestampExtender::new();
+        assert_eq!(extender.last(), None);
+
+        assert_eq!(
+            extender.extend(u32::MAX - 1),
+            SensorTime(u32::MAX as u64 - 1)
+        );
+        assert_eq!(extender.extend(u32::MAX), SensorTime(u32::MAX as u64));
+        assert_eq!(extender.extend(5), SensorTime((1 << 32) + 5));
+        assert_eq!(extender.extend(5), SensorTime((1 << 32) + 5));
+        assert_eq!(extender.extend(4), SensorTime((2 << 32) + 4));
+        assert_eq!(extender.last(), Some(SensorTime((2 << 32) + 4)));
+
+        extender.reset();
+        assert_eq!(extender.extend(4), SensorTime(4));
+    }
+
+    #[test]
+    fn correlation() {
+        let mut correlator = TimeCorrelator::new();
+        assert_eq!(correlator.offset_us(), None);
+        assert_eq!(correlator.to_host_us(SensorTime(0)), None);
+
+        // The host clock starts 5 s after the sensor and runs 100 ppm faster.
+        let host = |sensor_us: u64| 5_000_000 + sensor_us + sensor_us / 10_000;
+        correlator.add_pair(SensorTime(1_000), host(10_000));
+        assert_eq!(correlator.rate(), 1.0);
+        assert_eq!(correlator.offset_us(), Some(5_000_001));
+
+        for ticks in [101_000, 201_000, 301_000] {
+            correlator.add_pair(SensorTime(ticks), host(ticks * TIMESTAMP_TICK_US));
+        }
+        assert_eq!(correlator.pairs(), 4);
+        assert!((correlator.drift_ppm() - 100.0).abs() < 0.1);
+
+        let sensor_us = 5_000_000;
+        let mapped = correlator.to_host_us(SensorTime(sensor_us / TIMESTAMP_TICK_US));
+        assert!(mapped.unwrap().abs_diff(host(sensor_us)) <= 1);
+
+        correlator.reset();
+        assert_eq!(correlator.pairs(), 0);
+    }
+}